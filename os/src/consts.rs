@@ -12,9 +12,20 @@ pub const PHYSICAL_MEMORY_OFFSET: usize = 0xffffffff40000000;
 
 pub const PAGE_SIZE: usize = 4096;
 
+pub const SWAP_SIZE: usize = 0x100000;
+
 pub const KERNEL_STACK_SIZE: usize = 0x80000;
+// 内核栈区域被所有地址空间共享，每个槽位的高半部分为内核栈，低半部分不映射作为保护页
+// trap.asm 按这里的取值检测内核栈溢出，修改时需同步修改
+pub const KERNEL_STACK_REGION: usize = 0xffffffff80000000;
+pub const KERNEL_STACK_SLOT_SIZE: usize = KERNEL_STACK_SIZE * 2;
+pub const KERNEL_STACK_SLOTS: usize = 0x40000000 / KERNEL_STACK_SLOT_SIZE;
 
 pub const USER_STACK_SIZE: usize = 0x80000;
 pub const USER_STACK_OFFSET: usize = 0xffffffff00000000;
 
+// 未指定地址的 mmap 在 [USER_MMAP_BASE, USER_MMAP_END) 中寻找空闲空间
+pub const USER_MMAP_BASE: usize = 0x10_0000_0000;
+pub const USER_MMAP_END: usize = 0x40_0000_0000;
+
 pub const NOFILE: usize = 16;
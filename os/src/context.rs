@@ -2,6 +2,7 @@ use core::mem::zeroed;
 use riscv::register::sstatus;
 use riscv::register::{scause::Scause, sstatus::Sstatus};
 
+#[derive(Clone)]
 #[repr(C)]
 pub struct TrapFrame {
     pub x: [usize; 32],   // General registers
@@ -46,6 +47,10 @@ impl Context {
     ) -> Self {
         ContextContent::new_user_thread(entry, ustack_top, satp).push_at(kstack_top)
     }
+
+    pub unsafe fn new_fork(tf: &TrapFrame, kstack_top: usize, satp: usize) -> Self {
+        ContextContent::new_fork(tf, satp).push_at(kstack_top)
+    }
 }
 
 #[repr(C)]
@@ -97,6 +102,20 @@ impl ContextContent {
         }
     }
 
+    fn new_fork(tf: &TrapFrame, satp: usize) -> Self {
+        ContextContent {
+            ra: __trapret as usize,
+            satp,
+            s: [0; 12],
+            tf: {
+                let mut tf = tf.clone();
+                // 子线程中 fork 的返回值为 0
+                tf.x[10] = 0;
+                tf
+            },
+        }
+    }
+
     unsafe fn push_at(self, stack_top: usize) -> Context {
         let ptr = (stack_top as *mut ContextContent).sub(1);
         *ptr = self;
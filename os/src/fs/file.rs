@@ -1,13 +1,23 @@
-use crate::fs::ROOT_INODE;
+use crate::fs::pipe::PipeEnd;
 use alloc::sync::Arc;
 use rcore_fs::vfs::INode;
 
+// open 的标志位，取值与 Linux 保持一致
+pub const O_RDONLY: i32 = 0;
+pub const O_WRONLY: i32 = 1;
+pub const O_RDWR: i32 = 2;
+pub const O_ACCMODE: i32 = 3;
+pub const O_CREAT: i32 = 0o100;
+pub const O_TRUNC: i32 = 0o1000;
+pub const O_APPEND: i32 = 0o2000;
+
 #[derive(Copy, Clone, Debug)]
 pub enum FileDescriptorType {
     FdNone,
     FdInode,
     #[allow(dead_code)]
     FdDevice,
+    FdPipe,
 }
 
 #[derive(Clone)]
@@ -15,7 +25,10 @@ pub struct File {
     fdtype: FileDescriptorType,
     readable: bool,
     writable: bool,
+    // 每次写入前先将偏移移到文件末尾
+    append: bool,
     pub inode: Option<Arc<dyn INode>>,
+    pub pipe: Option<PipeEnd>,
     offset: usize,
 }
 
@@ -25,7 +38,9 @@ impl File {
             fdtype: FileDescriptorType::FdNone,
             readable: false,
             writable: false,
+            append: false,
             inode: None,
+            pipe: None,
             offset: 0,
         }
     }
@@ -41,6 +56,9 @@ impl File {
     pub fn get_writable(&self) -> bool {
         self.writable
     }
+    pub fn get_append(&self) -> bool {
+        self.append
+    }
     pub fn set_fdtype(&mut self, t: FileDescriptorType) {
         self.fdtype = t;
     }
@@ -54,19 +72,20 @@ impl File {
         self.offset
     }
 
-    #[allow(unused_unsafe)]
-    pub fn open_file(&mut self, path: &'static str, flags: i32) {
+    pub fn open_file(&mut self, inode: Arc<dyn INode>, flags: i32) {
         self.set_fdtype(FileDescriptorType::FdInode);
-        self.set_readable(true);
-        if (flags & 1) > 0 {
-            self.set_readable(false);
-        }
-        if (flags & 3) > 0 {
-            self.set_writable(true);
-        }
-        unsafe {
-            self.inode = Some(ROOT_INODE.lookup(path).unwrap().clone());
-        }
+        let mode = flags & O_ACCMODE;
+        self.set_readable(mode != O_WRONLY);
+        self.set_writable(mode == O_WRONLY || mode == O_RDWR);
+        self.append = flags & O_APPEND != 0;
+        self.inode = Some(inode);
         self.set_offset(0);
     }
+
+    pub fn open_pipe(&mut self, end: PipeEnd) {
+        self.set_fdtype(FileDescriptorType::FdPipe);
+        self.set_readable(!end.is_writable());
+        self.set_writable(end.is_writable());
+        self.pipe = Some(end);
+    }
 }
@@ -1,5 +1,7 @@
 mod device;
 pub mod file;
+pub mod path;
+pub mod pipe;
 pub mod stdio;
 
 use alloc::{sync::Arc, vec::Vec};
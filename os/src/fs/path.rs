@@ -0,0 +1,123 @@
+use super::{INodeExt, ROOT_INODE};
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+use rcore_fs::vfs::{FileType, FsError, INode, Result};
+
+// 解析路径时最多展开的符号链接层数，超过时认为出现了循环
+const MAX_SYMLINK_DEPTH: usize = 8;
+
+// 从根目录出发逐级查找，记录经过的各级目录名及其 inode
+// 遇到符号链接时展开，因此得到的总是不含符号链接的规范路径
+struct PathWalker {
+    names: Vec<String>,
+    inodes: Vec<Arc<dyn INode>>,
+}
+
+impl PathWalker {
+    fn new() -> Self {
+        PathWalker {
+            names: Vec::new(),
+            inodes: vec![ROOT_INODE.clone()],
+        }
+    }
+
+    fn current(&self) -> Arc<dyn INode> {
+        self.inodes.last().unwrap().clone()
+    }
+
+    // 依次走过 path 中的各级名称，depth 为已展开的符号链接层数
+    fn walk(&mut self, path: &str, depth: usize) -> Result<()> {
+        if path.starts_with('/') {
+            self.names.clear();
+            self.inodes.truncate(1);
+        }
+        let names: Vec<&str> = path
+            .split('/')
+            .filter(|name| !name.is_empty() && *name != ".")
+            .collect();
+        for name in names {
+            if name == ".." {
+                // 根目录的上一级仍是根目录
+                if !self.names.is_empty() {
+                    self.names.pop();
+                    self.inodes.pop();
+                }
+                continue;
+            }
+            let dir = self.current();
+            if dir.metadata()?.type_ != FileType::Dir {
+                return Err(FsError::NotDir);
+            }
+            let inode = dir.find(name)?;
+            if inode.metadata()?.type_ == FileType::SymLink {
+                if depth >= MAX_SYMLINK_DEPTH {
+                    return Err(FsError::SymLoop);
+                }
+                let target = inode.read_as_vec()?;
+                let target = core::str::from_utf8(&target).map_err(|_| FsError::InvalidParam)?;
+                self.walk(target, depth + 1)?;
+            } else {
+                self.names.push(String::from(name));
+                self.inodes.push(inode);
+            }
+        }
+        Ok(())
+    }
+
+    fn path(&self) -> String {
+        let mut path = String::new();
+        for name in self.names.iter() {
+            path.push('/');
+            path.push_str(name);
+        }
+        if path.is_empty() {
+            path.push('/');
+        }
+        path
+    }
+}
+
+// 相对路径从 cwd 开始查找，cwd 必须是规范的绝对路径
+fn walk(cwd: &str, path: &str) -> Result<PathWalker> {
+    if path.is_empty() {
+        return Err(FsError::EntryNotFound);
+    }
+    let mut walker = PathWalker::new();
+    if !path.starts_with('/') {
+        walker.walk(cwd, 0)?;
+    }
+    walker.walk(path, 0)?;
+    Ok(walker)
+}
+
+// 查找 path 所指的 inode，途中的符号链接都会被展开
+pub fn lookup(cwd: &str, path: &str) -> Result<Arc<dyn INode>> {
+    Ok(walk(cwd, path)?.current())
+}
+
+// 返回 path 的规范绝对路径及其 inode
+pub fn canonicalize(cwd: &str, path: &str) -> Result<(String, Arc<dyn INode>)> {
+    let walker = walk(cwd, path)?;
+    Ok((walker.path(), walker.current()))
+}
+
+// 查找 path 的上一级目录，返回该目录的 inode 与最后一级名称，用于创建或删除目录项
+pub fn lookup_parent(cwd: &str, path: &str) -> Result<(Arc<dyn INode>, String)> {
+    if path.is_empty() {
+        return Err(FsError::EntryNotFound);
+    }
+    let path = path.trim_end_matches('/');
+    let (dir, name) = match path.rfind('/') {
+        Some(0) => ("/", &path[1..]),
+        Some(i) => (&path[..i], &path[i + 1..]),
+        None => (".", path),
+    };
+    // 空路径、根目录以及 . 与 .. 都不能作为目录项的名称
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(FsError::InvalidParam);
+    }
+    let dir = lookup(cwd, dir)?;
+    if dir.metadata()?.type_ != FileType::Dir {
+        return Err(FsError::NotDir);
+    }
+    Ok((dir, String::from(name)))
+}
@@ -0,0 +1,139 @@
+use crate::sync::condvar::Condvar;
+use alloc::sync::Arc;
+use spin::Mutex;
+
+// 管道缓冲区的大小
+const PIPE_SIZE: usize = 4096;
+
+struct PipeBuffer {
+    data: [u8; PIPE_SIZE],
+    head: usize,
+    len: usize,
+    // 仍处于打开状态的读端与写端数量
+    readers: usize,
+    writers: usize,
+}
+
+pub struct Pipe {
+    buf: Mutex<PipeBuffer>,
+    // 缓冲区中有新数据或写端全部关闭
+    readable: Condvar,
+    // 缓冲区中有空闲位置或读端全部关闭
+    writable: Condvar,
+}
+
+impl Pipe {
+    // 创建一个管道，返回其读端与写端
+    pub fn new_pair() -> (PipeEnd, PipeEnd) {
+        let pipe = Arc::new(Pipe {
+            buf: Mutex::new(PipeBuffer {
+                data: [0; PIPE_SIZE],
+                head: 0,
+                len: 0,
+                readers: 0,
+                writers: 0,
+            }),
+            readable: Condvar::new(),
+            writable: Condvar::new(),
+        });
+        (PipeEnd::new(pipe.clone(), false), PipeEnd::new(pipe, true))
+    }
+
+    // 缓冲区为空时阻塞，所有写端关闭后返回 0 表示 EOF
+    pub fn read(&self, buf: &mut [u8]) -> usize {
+        if buf.is_empty() {
+            return 0;
+        }
+        loop {
+            let mut pipe = self.buf.lock();
+            if pipe.len > 0 {
+                let n = buf.len().min(pipe.len);
+                for byte in buf[..n].iter_mut() {
+                    *byte = pipe.data[pipe.head];
+                    pipe.head = (pipe.head + 1) % PIPE_SIZE;
+                }
+                pipe.len -= n;
+                drop(pipe);
+                self.writable.notify();
+                return n;
+            }
+            if pipe.writers == 0 {
+                return 0;
+            }
+            drop(pipe);
+            self.readable.wait();
+        }
+    }
+
+    // 缓冲区满时阻塞，直到全部写入；所有读端关闭后不再写入，返回已写入的字节数
+    pub fn write(&self, buf: &[u8]) -> usize {
+        let mut written = 0;
+        while written < buf.len() {
+            let mut pipe = self.buf.lock();
+            if pipe.readers == 0 {
+                break;
+            }
+            if pipe.len == PIPE_SIZE {
+                drop(pipe);
+                self.writable.wait();
+                continue;
+            }
+            let n = (buf.len() - written).min(PIPE_SIZE - pipe.len);
+            for &byte in buf[written..written + n].iter() {
+                let tail = (pipe.head + pipe.len) % PIPE_SIZE;
+                pipe.data[tail] = byte;
+                pipe.len += 1;
+            }
+            written += n;
+            drop(pipe);
+            self.readable.notify();
+        }
+        written
+    }
+}
+
+// 管道的一端，其创建与销毁维护着管道的读写端计数
+pub struct PipeEnd {
+    pub pipe: Arc<Pipe>,
+    writable: bool,
+}
+
+impl PipeEnd {
+    fn new(pipe: Arc<Pipe>, writable: bool) -> Self {
+        {
+            let mut buf = pipe.buf.lock();
+            if writable {
+                buf.writers += 1;
+            } else {
+                buf.readers += 1;
+            }
+        }
+        PipeEnd { pipe, writable }
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.writable
+    }
+}
+
+impl Clone for PipeEnd {
+    fn clone(&self) -> Self {
+        PipeEnd::new(self.pipe.clone(), self.writable)
+    }
+}
+
+impl Drop for PipeEnd {
+    fn drop(&mut self) {
+        let mut buf = self.pipe.buf.lock();
+        if self.writable {
+            buf.writers -= 1;
+            drop(buf);
+            // 唤醒所有等待的读者，让它们读到 EOF
+            self.pipe.readable.notify_all();
+        } else {
+            buf.readers -= 1;
+            drop(buf);
+            self.pipe.writable.notify_all();
+        }
+    }
+}
@@ -1,6 +1,9 @@
+use crate::consts::{PAGE_SIZE, USER_STACK_OFFSET};
 use crate::context::TrapFrame;
 use crate::memory::access_pa_via_va;
-use crate::process::tick;
+use crate::memory::memory_set::attr::AccessType;
+use crate::memory::swap;
+use crate::process::{current_tid, exit, handle_page_fault, tick};
 use crate::timer::clock_set_next_event;
 use riscv::register::sie;
 use riscv::register::{
@@ -50,6 +53,7 @@ pub unsafe fn enable_serial_interrupt() {
 
 #[no_mangle]
 pub fn rust_trap(tf: &mut TrapFrame) {
+    check_kernel_stack(tf);
     match tf.scause.cause() {
         Trap::Exception(Exception::Breakpoint) => breakpoint(&mut tf.sepc),
         Trap::Interrupt(Interrupt::SupervisorTimer) => super_timer(),
@@ -62,6 +66,21 @@ pub fn rust_trap(tf: &mut TrapFrame) {
     }
 }
 
+// trap.asm 发现内核栈溢出时会改用备用栈保存现场，此时已无法继续运行
+fn check_kernel_stack(tf: &TrapFrame) {
+    extern "C" {
+        fn kernel_overflow_stack();
+        fn kernel_overflow_stack_top();
+    }
+    let addr = tf as *const TrapFrame as usize;
+    if (kernel_overflow_stack as usize..kernel_overflow_stack_top as usize).contains(&addr) {
+        panic!(
+            "kernel stack overflow: sp = {:#x} instruction = {:#x}",
+            tf.x[2], tf.sepc
+        );
+    }
+}
+
 fn breakpoint(sepc: &mut usize) {
     println!("a breakpoint set @0x{:x}", sepc);
     *sepc += 2;
@@ -72,18 +91,37 @@ fn super_timer() {
     tick();
 }
 fn page_fault(tf: &mut TrapFrame) {
+    let access = match tf.scause.cause() {
+        Trap::Exception(Exception::InstructionPageFault) => AccessType::Execute,
+        Trap::Exception(Exception::StorePageFault) => AccessType::Write,
+        _ => AccessType::Read,
+    };
+    // 不属于任何进程的地址空间（如内核自己建立的）直接交给交换管理处理
+    if handle_page_fault(tf.stval, access) || swap::handle_page_fault(tf.stval, access) {
+        return;
+    }
     println!(
         "{:?} va = {:#x} instruction = {:#x}",
         tf.scause.cause(),
         tf.stval,
         tf.sepc
     );
+    // 用户程序访问非法地址或内存不足时只结束该进程
+    if tf.sstatus.spp() == sstatus::SPP::User {
+        // 用户栈下方的保护页不会被映射
+        if (USER_STACK_OFFSET - PAGE_SIZE..USER_STACK_OFFSET).contains(&tf.stval) {
+            println!("user stack overflow in tid {}", current_tid());
+        }
+        println!("thread {} killed by page fault", current_tid());
+        exit(-1isize as usize);
+    }
     panic!("page fault!");
 }
 
 fn syscall(tf: &mut TrapFrame) {
     tf.sepc += 4;
-    let ret = crate::syscall::syscall(tf.x[17], [tf.x[10], tf.x[11], tf.x[12]], tf);
+    let args = [tf.x[10], tf.x[11], tf.x[12], tf.x[13], tf.x[14], tf.x[15]];
+    let ret = crate::syscall::syscall(tf.x[17], args, tf);
     tf.x[10] = ret as usize;
 }
 
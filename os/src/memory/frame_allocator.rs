@@ -1,70 +1,206 @@
-use crate::consts::MAX_PHYSICAL_PAGES;
+use crate::consts::{MAX_PHYSICAL_MEMORY, MAX_PHYSICAL_PAGES, PHYSICAL_MEMORY_END};
 use spin::Mutex;
 
-pub struct SegmentTreeAllocator {
-    a: [u8; MAX_PHYSICAL_PAGES << 1],
-    m: usize,
+pub trait FrameAllocator {
+    // 管理页号位于 [l, r) 中的物理页帧
+    fn init(&mut self, l: usize, r: usize);
+    // 分配 cnt 个连续的物理页帧，返回第一个页帧的页号
+    fn alloc(&mut self, cnt: usize) -> Option<usize>;
+    fn dealloc(&mut self, ppn: usize, cnt: usize);
+}
+
+const BITMAP_WORDS: usize = MAX_PHYSICAL_PAGES / 64;
+
+// 首次适配：按地址从低到高找到第一段足够长的连续空闲页帧
+pub struct FirstFitAllocator {
+    // 每一位表示一个页帧是否已被占用
+    used: [u64; BITMAP_WORDS],
     n: usize,
     offset: usize,
 }
 
-impl SegmentTreeAllocator {
-    pub fn init(&mut self, l: usize, r: usize) {
-        self.offset = l - 1;
+impl FirstFitAllocator {
+    fn is_used(&self, i: usize) -> bool {
+        (self.used[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    fn set_used(&mut self, i: usize, value: bool) {
+        if value {
+            self.used[i / 64] |= 1 << (i % 64);
+        } else {
+            self.used[i / 64] &= !(1 << (i % 64));
+        }
+    }
+}
+
+impl FrameAllocator for FirstFitAllocator {
+    fn init(&mut self, l: usize, r: usize) {
+        assert!(l < r && r - l <= MAX_PHYSICAL_PAGES, "invalid frame range!");
+        self.offset = l;
         self.n = r - l;
-        self.m = 1;
-        while self.m < self.n + 2 {
-            self.m <<= 1;
+        for word in self.used.iter_mut() {
+            *word = 0;
         }
-        for i in 1..(self.m << 1) {
-            self.a[i] = 1;
+    }
+
+    fn alloc(&mut self, cnt: usize) -> Option<usize> {
+        // [start, i] 为当前这段连续的空闲页帧
+        let mut start = 0;
+        let mut i = 0;
+        while i < self.n {
+            // 整个字都已被占用时直接跳过
+            if i % 64 == 0 && self.used[i / 64] == !0 {
+                i += 64;
+                start = i;
+                continue;
+            }
+            if self.is_used(i) {
+                start = i + 1;
+            } else if i + 1 - start == cnt {
+                for j in start..=i {
+                    self.set_used(j, true);
+                }
+                return Some(start + self.offset);
+            }
+            i += 1;
         }
-        for i in 1..self.n {
-            self.a[self.m + i] = 0;
+        None
+    }
+
+    fn dealloc(&mut self, ppn: usize, cnt: usize) {
+        let start = ppn - self.offset;
+        for i in start..start + cnt {
+            assert!(self.is_used(i), "dealloc a free frame!");
+            self.set_used(i, false);
+        }
+    }
+}
+
+pub static FIRST_FIT_ALLOCATOR: Mutex<FirstFitAllocator> = Mutex::new(FirstFitAllocator {
+    used: [0; BITMAP_WORDS],
+    n: 0,
+    offset: 0,
+});
+
+// 伙伴系统：每次分配 2 的幂个页帧，释放时与相邻的伙伴合并
+pub struct BuddyAllocator {
+    // 完全二叉树，每个节点记录其子树中最大空闲块的阶数加一，0 表示没有空闲页帧
+    longest: [u8; MAX_PHYSICAL_PAGES << 1],
+    // 叶子个数向上取到 2 的幂，order 为根节点的阶数
+    order: usize,
+    offset: usize,
+}
+
+// 能容纳 cnt 个页帧的最小阶数
+fn order_of(cnt: usize) -> usize {
+    let mut order = 0;
+    while (1 << order) < cnt {
+        order += 1;
+    }
+    order
+}
+
+impl BuddyAllocator {
+    // 根据两个子节点计算阶数为 order 的节点，两个伙伴都完全空闲时合并
+    fn combine(&self, node: usize, order: usize) -> u8 {
+        let left = self.longest[node << 1];
+        let right = self.longest[(node << 1) | 1];
+        if left as usize == order && right as usize == order {
+            (order + 1) as u8
+        } else {
+            left.max(right)
         }
-        for i in (1..self.m).rev() {
-            self.a[i] = self.a[i << 1] & self.a[(i << 1) | 1];
+    }
+
+    fn update(&mut self, mut node: usize, mut order: usize) {
+        while node > 1 {
+            node >>= 1;
+            order += 1;
+            self.longest[node] = self.combine(node, order);
         }
     }
+}
 
-    pub fn alloc(&mut self) -> usize {
-        // assume that we never run out of physical memory
-        if self.a[1] == 1 {
-            panic!("physical memory depleted!");
+impl FrameAllocator for BuddyAllocator {
+    fn init(&mut self, l: usize, r: usize) {
+        assert!(l < r && r - l <= MAX_PHYSICAL_PAGES, "invalid frame range!");
+        let n = r - l;
+        self.offset = l;
+        self.order = order_of(n);
+        let m = 1 << self.order;
+        for k in 0..m {
+            self.longest[m + k] = if k < n { 1 } else { 0 };
         }
-        let mut p = 1;
-        while p < self.m {
-            if self.a[p << 1] == 0 {
-                p <<= 1;
-            } else {
-                p = (p << 1) | 1;
+        let mut order = 1;
+        let mut begin = m >> 1;
+        while begin > 0 {
+            for node in begin..(begin << 1) {
+                self.longest[node] = self.combine(node, order);
             }
+            order += 1;
+            begin >>= 1;
         }
-        let result = p + self.offset - self.m;
-        self.a[p] = 1;
-        p >>= 1;
-        while p > 0 {
-            self.a[p] = self.a[p << 1] & self.a[(p << 1) | 1];
-            p >>= 1;
-        }
-        result
     }
 
-    pub fn dealloc(&mut self, n: usize) {
-        let mut p = n + self.m - self.offset;
-        assert!(self.a[p] == 1);
-        self.a[p] = 0;
-        p >>= 1;
-        while p > 0 {
-            self.a[p] = self.a[p << 1] & self.a[(p << 1) | 1];
-            p >>= 1;
+    fn alloc(&mut self, cnt: usize) -> Option<usize> {
+        let order = order_of(cnt);
+        if order > self.order || (self.longest[1] as usize) < order + 1 {
+            return None;
+        }
+        let mut node = 1;
+        let mut node_order = self.order;
+        while node_order > order {
+            node <<= 1;
+            if (self.longest[node] as usize) < order + 1 {
+                node += 1;
+            }
+            node_order -= 1;
         }
+        self.longest[node] = 0;
+        self.update(node, order);
+        Some(((node - (1 << (self.order - order))) << order) + self.offset)
+    }
+
+    fn dealloc(&mut self, ppn: usize, cnt: usize) {
+        let order = order_of(cnt);
+        let node = ((ppn - self.offset) >> order) + (1 << (self.order - order));
+        assert!(self.longest[node] == 0, "dealloc a free frame!");
+        self.longest[node] = (order + 1) as u8;
+        self.update(node, order);
     }
 }
 
-pub static SEGMENT_TREE_ALLOCATOR: Mutex<SegmentTreeAllocator> = Mutex::new(SegmentTreeAllocator {
-    a: [0; MAX_PHYSICAL_PAGES << 1],
-    m: 0,
-    n: 0,
+pub static BUDDY_ALLOCATOR: Mutex<BuddyAllocator> = Mutex::new(BuddyAllocator {
+    longest: [0; MAX_PHYSICAL_PAGES << 1],
+    order: 0,
     offset: 0,
 });
+
+// 物理内存起始处的物理页号
+const BASE_PPN: usize = (PHYSICAL_MEMORY_END - MAX_PHYSICAL_MEMORY) >> 12;
+
+// 记录每个物理页帧被多少个页表项所引用，供写时复制使用
+pub struct FrameRefCounter {
+    count: [u16; MAX_PHYSICAL_PAGES],
+}
+
+impl FrameRefCounter {
+    pub fn get(&self, n: usize) -> usize {
+        self.count[n - BASE_PPN] as usize
+    }
+
+    pub fn inc(&mut self, n: usize) {
+        self.count[n - BASE_PPN] += 1;
+    }
+
+    pub fn dec(&mut self, n: usize) -> usize {
+        let count = &mut self.count[n - BASE_PPN];
+        assert!(*count > 0, "frame reference count underflow!");
+        *count -= 1;
+        *count as usize
+    }
+}
+
+pub static FRAME_REF_COUNTER: Mutex<FrameRefCounter> = Mutex::new(FrameRefCounter {
+    count: [0; MAX_PHYSICAL_PAGES],
+});
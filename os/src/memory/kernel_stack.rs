@@ -0,0 +1,74 @@
+use crate::consts::{
+    KERNEL_STACK_REGION, KERNEL_STACK_SIZE, KERNEL_STACK_SLOTS, KERNEL_STACK_SLOT_SIZE,
+};
+use crate::memory::paging::{PageRange, PageTableImpl};
+use crate::memory::{alloc_frame, dealloc_frame, OutOfMemory};
+use alloc::{vec, vec::Vec};
+use lazy_static::*;
+use spin::Mutex;
+
+struct KernelStackRegion {
+    // 只用于在内核栈区域中建立映射，该区域的二级页表被所有页表共享
+    table: PageTableImpl,
+    // 各槽位是否被占用
+    used: Vec<bool>,
+}
+
+impl KernelStackRegion {
+    // 撤销 [bottom, end) 的映射并逐页释放页帧
+    fn unmap(&mut self, bottom: usize, end: usize) {
+        for va in PageRange::new(bottom, end) {
+            dealloc_frame(self.table.unmap(va));
+        }
+    }
+}
+
+lazy_static! {
+    static ref REGION: Mutex<KernelStackRegion> = Mutex::new(KernelStackRegion {
+        table: PageTableImpl::new_shared().expect("failed to create kernel stack region!"),
+        used: vec![false; KERNEL_STACK_SLOTS],
+    });
+}
+
+// 必须在建立其他页表之前调用，之后建立的页表才能看到内核栈区域
+pub fn init() {
+    lazy_static::initialize(&REGION);
+}
+
+// 逐页分配页帧作为内核栈，映射到空闲槽位的高半部分，返回栈底地址
+pub fn alloc() -> Result<usize, OutOfMemory> {
+    let mut region = REGION.lock();
+    let slot = region
+        .used
+        .iter()
+        .position(|used| !used)
+        .ok_or(OutOfMemory)?;
+    let bottom = KERNEL_STACK_REGION + (slot + 1) * KERNEL_STACK_SLOT_SIZE - KERNEL_STACK_SIZE;
+    for va in PageRange::new(bottom, bottom + KERNEL_STACK_SIZE) {
+        let frame = match alloc_frame() {
+            Some(frame) => frame,
+            None => {
+                region.unmap(bottom, va);
+                return Err(OutOfMemory);
+            }
+        };
+        if region
+            .table
+            .map(va, frame.start_address().as_usize())
+            .is_err()
+        {
+            dealloc_frame(frame);
+            region.unmap(bottom, va);
+            return Err(OutOfMemory);
+        }
+    }
+    region.used[slot] = true;
+    Ok(bottom)
+}
+
+pub fn dealloc(bottom: usize) {
+    let mut region = REGION.lock();
+    region.unmap(bottom, bottom + KERNEL_STACK_SIZE);
+    let slot = (bottom - KERNEL_STACK_REGION) / KERNEL_STACK_SLOT_SIZE;
+    region.used[slot] = false;
+}
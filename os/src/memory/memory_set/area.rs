@@ -1,6 +1,10 @@
-use super::{attr::MemoryAttr, handler::MemoryHandler};
+use super::{
+    attr::{AccessType, MemoryAttr},
+    handler::MemoryHandler,
+};
 use crate::consts::PAGE_SIZE;
 use crate::memory::paging::{PageRange, PageTableImpl};
+use crate::memory::{swap, OutOfMemory};
 use alloc::boxed::Box;
 
 #[derive(Debug, Clone)]
@@ -12,19 +16,153 @@ pub struct MemoryArea {
 }
 
 impl MemoryArea {
-    pub fn map(&self, pt: &mut PageTableImpl) {
+    // 映射失败时撤销该区域中已经建立的映射
+    pub fn map(&self, pt: &mut PageTableImpl) -> Result<(), OutOfMemory> {
         for page in PageRange::new(self.start, self.end) {
-            self.handler.map(pt, page, &self.attr);
+            if let Err(err) = self.handler.map(pt, page, &self.attr) {
+                self.unmap_before(pt, page);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn clone_map(
+        &self,
+        pt: &mut PageTableImpl,
+        src_pt: &mut PageTableImpl,
+    ) -> Result<(), OutOfMemory> {
+        for page in PageRange::new(self.start, self.end) {
+            if let Err(err) = self.handler.clone_map(pt, src_pt, page, &self.attr) {
+                self.unmap_before(pt, page);
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn unmap(&self, pt: &mut PageTableImpl) {
+        for page in PageRange::new(self.start, self.end) {
+            self.handler.unmap(pt, page);
         }
     }
 
-    #[allow(dead_code)]
-    fn unmap(&self, pt: &mut PageTableImpl) {
+    // 调整区域的结束地址，新增的页交给 handler 映射，移出的页撤销映射
+    pub fn resize(&mut self, pt: &mut PageTableImpl, end: usize) -> Result<(), OutOfMemory> {
+        let old_end = self.page_end();
+        // 地址向上取整时溢出说明无法容纳这么大的区域
+        let new_end = end.checked_add(PAGE_SIZE - 1).ok_or(OutOfMemory)? / PAGE_SIZE * PAGE_SIZE;
+        for page in PageRange::new(new_end.min(old_end), old_end) {
+            self.handler.unmap(pt, page);
+        }
+        for page in PageRange::new(old_end, new_end.max(old_end)) {
+            if let Err(err) = self.handler.map(pt, page, &self.attr) {
+                for mapped in PageRange::new(old_end, page) {
+                    self.handler.unmap(pt, mapped);
+                }
+                return Err(err);
+            }
+        }
+        self.end = end;
+        Ok(())
+    }
+
+    // 将 [start, end) 与本区域重叠部分中被修改过的页面写回
+    pub fn sync(&self, pt: &mut PageTableImpl, start: usize, end: usize) {
+        let start = self.start.max(start);
+        let end = self.end.min(end);
+        if start < end {
+            for page in PageRange::new(start, end) {
+                self.handler.sync(pt, page);
+            }
+        }
+    }
+
+    // 修改区域的访问权限并更新已映射的页表项
+    // 变为可写的页面不直接修改页表项，首次写入时再由缺页处理（写时复制）设为可写
+    // 已被换出的页面同样要更新，换入时沿用其页表项中的权限
+    pub fn protect(&mut self, pt: &mut PageTableImpl, attr: MemoryAttr) {
         for page in PageRange::new(self.start, self.end) {
+            let swapped = swap::is_swapped(pt, page);
+            if let Some(entry) = pt.get_entry(page) {
+                if entry.present() || swapped {
+                    entry.set_user(attr.is_user());
+                    entry.set_execute(attr.allows(AccessType::Execute));
+                    if !attr.allows(AccessType::Write) {
+                        entry.set_writable(false);
+                    }
+                    entry.update();
+                }
+            }
+        }
+        self.attr = attr;
+    }
+
+    // 以页对齐的 [start, end) 为界将区域切成至多三段，返回 (左侧, 中间, 右侧)
+    pub fn split(self, start: usize, end: usize) -> (Option<Self>, Self, Option<Self>) {
+        let mut middle = self;
+        let left = if middle.start < start {
+            let right = middle.split_off(start);
+            Some(core::mem::replace(&mut middle, right))
+        } else {
+            None
+        };
+        let right = if middle.end > end {
+            Some(middle.split_off(end))
+        } else {
+            None
+        };
+        (left, middle, right)
+    }
+
+    // 自身保留 [start, at)，返回 [at, end)
+    fn split_off(&mut self, at: usize) -> Self {
+        let right = MemoryArea::new(at, self.end, self.handler.clone(), self.attr.clone());
+        self.end = at;
+        right
+    }
+
+    pub fn is_user(&self) -> bool {
+        self.attr.is_user()
+    }
+
+    pub fn need_unmap(&self) -> bool {
+        self.handler.need_unmap()
+    }
+
+    pub fn allows_write(&self) -> bool {
+        self.handler.allows_write()
+    }
+
+    // 撤销 end 之前各页的映射
+    fn unmap_before(&self, pt: &mut PageTableImpl, end: usize) {
+        for page in PageRange::new(self.start, self.end).take_while(|&page| page < end) {
             self.handler.unmap(pt, page);
         }
     }
 
+    pub fn contains(&self, va: usize) -> bool {
+        self.is_overlap_with(va, va + 1)
+    }
+
+    // 区域所覆盖的最后一页的结束地址
+    pub fn page_end(&self) -> usize {
+        (self.end - 1) / PAGE_SIZE * PAGE_SIZE + PAGE_SIZE
+    }
+
+    // 用户态能否以这种方式访问该区域
+    pub fn user_allows(&self, access: AccessType) -> bool {
+        self.attr.is_user() && self.attr.allows(access)
+    }
+
+    pub fn handle_page_fault(&self, pt: &mut PageTableImpl, va: usize, access: AccessType) -> bool {
+        if !self.attr.allows(access) {
+            return false;
+        }
+        self.handler
+            .handle_page_fault(pt, va / PAGE_SIZE * PAGE_SIZE, access, &self.attr)
+    }
+
     pub fn is_overlap_with(&self, start_addr: usize, end_addr: usize) -> bool {
         let p1 = self.start / PAGE_SIZE;
         let p2 = (self.end - 1) / PAGE_SIZE + 1;
@@ -47,19 +185,25 @@ impl MemoryArea {
         }
     }
 
-    pub fn page_copy(&self, pt: &mut PageTableImpl, src: usize, length: usize) {
+    pub fn page_copy(
+        &self,
+        pt: &mut PageTableImpl,
+        src: usize,
+        length: usize,
+    ) -> Result<(), OutOfMemory> {
         let mut l = length;
         let mut s = src;
         let mut offset = self.start % PAGE_SIZE;
         for page in PageRange::new(self.start, self.end) {
             let copy_size = PAGE_SIZE-offset;
             self.handler
-                .page_copy(pt, page, offset, s, if l < copy_size { l } else { copy_size });
+                .page_copy(pt, page, offset, s, if l < copy_size { l } else { copy_size }, &self.attr)?;
             offset = 0;
             s += copy_size;
             if l >= copy_size {
                 l -= copy_size;
             }
         }
+        Ok(())
     }
 }
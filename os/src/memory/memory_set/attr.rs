@@ -1,5 +1,12 @@
 use crate::memory::paging::PageEntry;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessType {
+    Read,
+    Write,
+    Execute,
+}
+
 #[derive(Clone, Default, Debug)]
 pub struct MemoryAttr {
     user: bool,
@@ -8,6 +15,9 @@ pub struct MemoryAttr {
 }
 
 impl MemoryAttr {
+    pub fn new() -> Self {
+        Self::default()
+    }
     pub fn set_user(mut self) -> Self {
         self.user = true;
         self
@@ -21,6 +31,19 @@ impl MemoryAttr {
         self
     }
 
+    pub fn is_user(&self) -> bool {
+        self.user
+    }
+
+    // 判断该区域是否允许这种访问
+    pub fn allows(&self, access: AccessType) -> bool {
+        match access {
+            AccessType::Read => true,
+            AccessType::Write => !self.readonly,
+            AccessType::Execute => self.execute,
+        }
+    }
+
     pub fn apply(&self, entry: &mut PageEntry) {
         entry.set_present(true);
         entry.set_user(self.user);
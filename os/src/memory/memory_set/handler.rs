@@ -1,16 +1,57 @@
-use super::attr::MemoryAttr;
+use super::attr::{AccessType, MemoryAttr};
 use crate::consts::PAGE_SIZE;
 use crate::memory::access_pa_via_va;
-use crate::memory::alloc_frame;
-use crate::memory::paging::PageTableImpl;
+use crate::memory::paging::{PageEntry, PageTableImpl};
+use crate::memory::swap;
+use crate::memory::{
+    alloc_frame, dealloc_frame, frame_ref_count, frame_ref_dec, frame_ref_inc, OutOfMemory,
+};
 use alloc::boxed::Box;
-use core::fmt::Debug;
+use alloc::sync::Arc;
+use core::fmt::{self, Debug, Formatter};
+use rcore_fs::vfs::INode;
+use riscv::addr::{Frame, PhysAddr};
 
 pub trait MemoryHandler: Debug + 'static {
     fn box_clone(&self) -> Box<dyn MemoryHandler>;
-    fn map(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr);
+    fn map(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) -> Result<(), OutOfMemory>;
+    // 撤销映射并释放该页占用的页帧
     fn unmap(&self, pt: &mut PageTableImpl, va: usize);
-    fn page_copy(&self, pt: &mut PageTableImpl, va: usize, va_offset: usize, src: usize, length: usize);
+    // 将数据复制到 va 所在的页中，attr 为所在区域的属性，页面尚未分配时按它建立映射
+    fn page_copy(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        va_offset: usize,
+        src: usize,
+        length: usize,
+        attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory>;
+    fn clone_map(
+        &self,
+        pt: &mut PageTableImpl,
+        src_pt: &mut PageTableImpl,
+        va: usize,
+        attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory>;
+    // 处理落在该页上的缺页异常，返回是否处理成功
+    fn handle_page_fault(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        access: AccessType,
+        attr: &MemoryAttr,
+    ) -> bool;
+    // 销毁地址空间时是否需要逐页 unmap
+    fn need_unmap(&self) -> bool {
+        true
+    }
+    // 将该页中被修改的内容写回后备存储
+    fn sync(&self, _pt: &mut PageTableImpl, _va: usize) {}
+    // 能否通过 mprotect 为该区域加上写权限
+    fn allows_write(&self) -> bool {
+        true
+    }
 }
 
 impl Clone for Box<dyn MemoryHandler> {
@@ -33,13 +74,22 @@ impl MemoryHandler for Linear {
     fn box_clone(&self) -> Box<dyn MemoryHandler> {
         Box::new(self.clone())
     }
-    fn map(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) {
-        attr.apply(pt.map(va, va - self.offset));
+    fn map(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) -> Result<(), OutOfMemory> {
+        attr.apply(pt.map(va, va - self.offset)?);
+        Ok(())
     }
     fn unmap(&self, pt: &mut PageTableImpl, va: usize) {
         pt.unmap(va);
     }
-    fn page_copy(&self, pt: &mut PageTableImpl, va: usize, va_offset: usize, src: usize, length: usize) {
+    fn page_copy(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        va_offset: usize,
+        src: usize,
+        length: usize,
+        _attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
         let pa = pt.get_entry(va).expect("get pa error!").0.addr().as_usize();
         assert!(va == access_pa_via_va(pa));
         assert!(va == pa + self.offset);
@@ -54,42 +104,684 @@ impl MemoryHandler for Linear {
                 dst[i] = 0;
             }
         }
+        Ok(())
+    }
+    fn clone_map(
+        &self,
+        pt: &mut PageTableImpl,
+        _src_pt: &mut PageTableImpl,
+        va: usize,
+        attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        self.map(pt, va, attr)
+    }
+    fn handle_page_fault(
+        &self,
+        _pt: &mut PageTableImpl,
+        _va: usize,
+        _access: AccessType,
+        _attr: &MemoryAttr,
+    ) -> bool {
+        false
+    }
+    // 线性映射不占用页帧，随页表一起回收即可
+    fn need_unmap(&self) -> bool {
+        false
+    }
+}
+
+// 保护页：只占据地址空间而不建立映射，任何访问都会引发无法处理的缺页异常
+#[derive(Debug, Clone)]
+pub struct Guard;
+impl Guard {
+    pub fn new() -> Self {
+        Guard {}
+    }
+}
+impl MemoryHandler for Guard {
+    fn box_clone(&self) -> Box<dyn MemoryHandler> {
+        Box::new(self.clone())
+    }
+    fn map(
+        &self,
+        _pt: &mut PageTableImpl,
+        _va: usize,
+        _attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        Ok(())
+    }
+    fn unmap(&self, _pt: &mut PageTableImpl, _va: usize) {}
+    fn page_copy(
+        &self,
+        _pt: &mut PageTableImpl,
+        _va: usize,
+        _va_offset: usize,
+        _src: usize,
+        _length: usize,
+        _attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        Ok(())
+    }
+    fn clone_map(
+        &self,
+        _pt: &mut PageTableImpl,
+        _src_pt: &mut PageTableImpl,
+        _va: usize,
+        _attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        Ok(())
+    }
+    fn handle_page_fault(
+        &self,
+        _pt: &mut PageTableImpl,
+        _va: usize,
+        _access: AccessType,
+        _attr: &MemoryAttr,
+    ) -> bool {
+        false
+    }
+    fn need_unmap(&self) -> bool {
+        false
     }
 }
 
+// 写时复制：fork 时父子进程共享同一物理页帧并将其设为只读，
+// 任何一方写入时才真正复制一份；只被一个页表引用的页帧可能被换出
 #[derive(Debug, Clone)]
-pub struct ByFrame;
-impl ByFrame {
+pub struct ByFrameCow;
+impl ByFrameCow {
     pub fn new() -> Self {
-        ByFrame {}
+        ByFrameCow {}
     }
 }
-impl MemoryHandler for ByFrame {
+impl MemoryHandler for ByFrameCow {
     fn box_clone(&self) -> Box<dyn MemoryHandler> {
         Box::new(self.clone())
     }
 
-    fn map(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) {
-        let frame = alloc_frame().expect("alloc_frame failed!");
+    fn map(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) -> Result<(), OutOfMemory> {
+        let frame = alloc_frame().ok_or(OutOfMemory)?;
+        attr.apply(map_or_free(pt, va, frame)?);
+        frame_ref_inc(frame);
+        swap::track(pt, va);
+        Ok(())
+    }
+
+    fn unmap(&self, pt: &mut PageTableImpl, va: usize) {
+        swap_unmap(pt, va);
+    }
+    fn page_copy(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        va_offset: usize,
+        src: usize,
+        length: usize,
+        _attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        let pa = swap_ensure(pt, va)?;
+        unsafe {
+            fill_frame(pa, va_offset, src, length);
+        }
+        Ok(())
+    }
+    fn clone_map(
+        &self,
+        pt: &mut PageTableImpl,
+        src_pt: &mut PageTableImpl,
+        va: usize,
+        attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        swap_share(pt, src_pt, va, attr)
+    }
+    fn handle_page_fault(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        access: AccessType,
+        attr: &MemoryAttr,
+    ) -> bool {
+        // 被换出的页面只需换入，若仍需写时复制，重新执行的访问会再次引发缺页
+        if !is_present(pt, va) {
+            return swap::swap_in(pt, va, AccessType::Read);
+        }
+        is_write_fault(access, attr) && cow_write(pt, va)
+    }
+}
+
+// 按需分配：映射时只保留虚拟地址范围，第一次访问某页时才分配并清零页帧
+// 已分配的页帧在 fork 时同样采用写时复制，并且同样可能被换出
+#[derive(Debug, Clone)]
+pub struct ByFrameLazy;
+impl ByFrameLazy {
+    pub fn new() -> Self {
+        ByFrameLazy {}
+    }
+}
+impl MemoryHandler for ByFrameLazy {
+    fn box_clone(&self) -> Box<dyn MemoryHandler> {
+        Box::new(self.clone())
+    }
+
+    fn map(
+        &self,
+        _pt: &mut PageTableImpl,
+        _va: usize,
+        _attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        Ok(())
+    }
+
+    fn unmap(&self, pt: &mut PageTableImpl, va: usize) {
+        swap_unmap(pt, va);
+    }
+    fn page_copy(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        va_offset: usize,
+        src: usize,
+        length: usize,
+        attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        if !is_present(pt, va) && !swap::is_swapped(pt, va) {
+            lazy_alloc(pt, va, attr)?;
+        }
+        let pa = swap_ensure(pt, va)?;
+        unsafe {
+            fill_frame(pa, va_offset, src, length);
+        }
+        Ok(())
+    }
+    fn clone_map(
+        &self,
+        pt: &mut PageTableImpl,
+        src_pt: &mut PageTableImpl,
+        va: usize,
+        attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        swap_share(pt, src_pt, va, attr)
+    }
+    fn handle_page_fault(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        access: AccessType,
+        attr: &MemoryAttr,
+    ) -> bool {
+        if is_present(pt, va) {
+            is_write_fault(access, attr) && cow_write(pt, va)
+        } else if swap::is_swapped(pt, va) {
+            swap::swap_in(pt, va, AccessType::Read)
+        } else {
+            lazy_alloc(pt, va, attr).is_ok()
+        }
+    }
+}
+
+// 文件映射：缺页时经由 INode::read_at 读入文件内容
+// 共享映射在撤销或同步时将被修改过的页面写回文件，私有映射则写时复制
+#[derive(Clone)]
+pub struct ByFile {
+    inode: Arc<dyn INode>,
+    // 虚拟地址 base 对应文件中的 offset 处
+    base: usize,
+    offset: usize,
+    shared: bool,
+    // 建立映射时文件是否以可写方式打开，否则共享映射不能被写入
+    writable: bool,
+}
+impl ByFile {
+    pub fn new(
+        inode: Arc<dyn INode>,
+        base: usize,
+        offset: usize,
+        shared: bool,
+        writable: bool,
+    ) -> Self {
+        ByFile {
+            inode,
+            base,
+            offset,
+            shared,
+            writable,
+        }
+    }
+
+    // 分配页帧并读入 va 所在页面的文件内容，超出文件末尾的部分为 0
+    fn load(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) -> bool {
+        let frame = match alloc_frame() {
+            Some(frame) => frame,
+            None => return false,
+        };
         let pa = frame.start_address().as_usize();
-        attr.apply(pt.map(va, pa));
+        let data =
+            unsafe { core::slice::from_raw_parts_mut(access_pa_via_va(pa) as *mut u8, PAGE_SIZE) };
+        for byte in data.iter_mut() {
+            *byte = 0;
+        }
+        if self.inode.read_at(self.file_offset(va), data).is_err() {
+            dealloc_frame(frame);
+            return false;
+        }
+        match map_or_free(pt, va, frame) {
+            Ok(entry) => attr.apply(entry),
+            Err(_) => return false,
+        }
+        frame_ref_inc(frame);
+        true
+    }
+
+    fn file_offset(&self, va: usize) -> usize {
+        self.offset + (va - self.base)
+    }
+}
+impl Debug for ByFile {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("ByFile")
+            .field("base", &self.base)
+            .field("offset", &self.offset)
+            .field("shared", &self.shared)
+            .field("writable", &self.writable)
+            .finish()
+    }
+}
+impl MemoryHandler for ByFile {
+    fn box_clone(&self) -> Box<dyn MemoryHandler> {
+        Box::new(self.clone())
+    }
+
+    fn map(
+        &self,
+        _pt: &mut PageTableImpl,
+        _va: usize,
+        _attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        Ok(())
     }
 
     fn unmap(&self, pt: &mut PageTableImpl, va: usize) {
-        pt.unmap(va);
+        self.sync(pt, va);
+        cow_unmap(pt, va);
     }
-    fn page_copy(&self, pt: &mut PageTableImpl, va: usize, va_offset: usize, src: usize, length: usize) {
-        let pa = pt.get_entry(va).expect("get pa error!").0.addr().as_usize();
+    fn page_copy(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        va_offset: usize,
+        src: usize,
+        length: usize,
+        attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        if !is_present(pt, va) && !self.load(pt, va, attr) {
+            return Err(OutOfMemory);
+        }
+        let pa = pt.get_entry(va).expect("get pa error!").target();
         unsafe {
-            let dst = core::slice::from_raw_parts_mut((access_pa_via_va(pa)+va_offset) as *mut u8, PAGE_SIZE);
-            if length > 0 {
-                let src = core::slice::from_raw_parts(src as *const u8, PAGE_SIZE);
-                dst[..length].clone_from_slice(&src[..length]);
-            }
-            #[allow(clippy::needless_range_loop)]
-            for i in length..PAGE_SIZE {
-                dst[i] = 0;
+            fill_frame(pa, va_offset, src, length);
+        }
+        Ok(())
+    }
+    // 共享映射的父子进程使用同一页帧，私有映射则写时复制
+    fn clone_map(
+        &self,
+        pt: &mut PageTableImpl,
+        src_pt: &mut PageTableImpl,
+        va: usize,
+        attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        if !is_present(src_pt, va) {
+            return Ok(());
+        }
+        if !self.shared {
+            return cow_share(pt, src_pt, va, attr);
+        }
+        let src_entry = src_pt.get_entry(va).expect("get src entry error!");
+        let pa = src_entry.target();
+        let writable = src_entry.writable();
+        let entry = pt.map(va, pa)?;
+        attr.apply(entry);
+        entry.set_writable(writable);
+        frame_ref_inc(Frame::of_addr(PhysAddr::new(pa)));
+        Ok(())
+    }
+    fn handle_page_fault(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        access: AccessType,
+        attr: &MemoryAttr,
+    ) -> bool {
+        if !is_present(pt, va) {
+            return self.load(pt, va, attr);
+        }
+        if !is_write_fault(access, attr) {
+            return false;
+        }
+        if !self.shared {
+            return cow_write(pt, va);
+        }
+        // 共享页面不复制，mprotect 恢复写权限后直接设为可写
+        let entry = pt.get_entry(va).expect("get entry error!");
+        if entry.writable() || !self.writable {
+            return false;
+        }
+        entry.set_writable(true);
+        entry.update();
+        true
+    }
+    fn sync(&self, pt: &mut PageTableImpl, va: usize) {
+        if !self.shared {
+            return;
+        }
+        let entry = match pt.get_entry(va) {
+            Some(entry) if entry.present() && entry.dirty() => entry,
+            _ => return,
+        };
+        // 只写回文件范围内的部分，不改变文件大小
+        let offset = self.file_offset(va);
+        let size = match self.inode.metadata() {
+            Ok(metadata) => metadata.size,
+            Err(_) => return,
+        };
+        if offset < size {
+            let len = (size - offset).min(PAGE_SIZE);
+            let data = unsafe {
+                core::slice::from_raw_parts(access_pa_via_va(entry.target()) as *const u8, len)
+            };
+            if self.inode.write_at(offset, data).is_err() {
+                return;
             }
         }
+        entry.clear_dirty();
+        entry.update();
+    }
+    fn allows_write(&self) -> bool {
+        !self.shared || self.writable
+    }
+}
+
+// 可换出：映射时即分配页帧，物理页帧不足时可能被时钟算法换出到交换区
+#[derive(Debug, Clone)]
+pub struct ByFrameSwappingOut;
+#[allow(dead_code)]
+impl ByFrameSwappingOut {
+    pub fn new() -> Self {
+        ByFrameSwappingOut {}
+    }
+}
+impl MemoryHandler for ByFrameSwappingOut {
+    fn box_clone(&self) -> Box<dyn MemoryHandler> {
+        Box::new(self.clone())
+    }
+
+    fn map(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) -> Result<(), OutOfMemory> {
+        let frame = alloc_frame().ok_or(OutOfMemory)?;
+        attr.apply(map_or_free(pt, va, frame)?);
+        frame_ref_inc(frame);
+        swap::track(pt, va);
+        Ok(())
+    }
+
+    fn unmap(&self, pt: &mut PageTableImpl, va: usize) {
+        swap_unmap(pt, va);
+    }
+    fn page_copy(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        va_offset: usize,
+        src: usize,
+        length: usize,
+        _attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        let pa = swap_ensure(pt, va)?;
+        unsafe {
+            fill_frame(pa, va_offset, src, length);
+        }
+        Ok(())
+    }
+    fn clone_map(
+        &self,
+        pt: &mut PageTableImpl,
+        src_pt: &mut PageTableImpl,
+        va: usize,
+        attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        swap_copy(pt, src_pt, va, attr)
+    }
+    fn handle_page_fault(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        _access: AccessType,
+        _attr: &MemoryAttr,
+    ) -> bool {
+        // 访问权限已由所在区域检查过
+        !is_present(pt, va) && swap::swap_in(pt, va, AccessType::Read)
+    }
+}
+
+// 按需分配：第一次访问某页时通过页面置换换出一个页面，直接使用它的页帧
+#[derive(Debug, Clone)]
+pub struct ByFrameWithRpa;
+#[allow(dead_code)]
+impl ByFrameWithRpa {
+    pub fn new() -> Self {
+        ByFrameWithRpa {}
+    }
+}
+impl MemoryHandler for ByFrameWithRpa {
+    fn box_clone(&self) -> Box<dyn MemoryHandler> {
+        Box::new(self.clone())
+    }
+
+    fn map(&self, pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) -> Result<(), OutOfMemory> {
+        swap::reserve(pt, va, attr);
+        Ok(())
+    }
+
+    fn unmap(&self, pt: &mut PageTableImpl, va: usize) {
+        swap_unmap(pt, va);
+    }
+    fn page_copy(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        va_offset: usize,
+        src: usize,
+        length: usize,
+        _attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        let pa = swap_ensure(pt, va)?;
+        unsafe {
+            fill_frame(pa, va_offset, src, length);
+        }
+        Ok(())
     }
+    fn clone_map(
+        &self,
+        pt: &mut PageTableImpl,
+        src_pt: &mut PageTableImpl,
+        va: usize,
+        attr: &MemoryAttr,
+    ) -> Result<(), OutOfMemory> {
+        if swap::is_unallocated(src_pt, va) {
+            swap::reserve(pt, va, attr);
+            Ok(())
+        } else {
+            swap_copy(pt, src_pt, va, attr)
+        }
+    }
+    fn handle_page_fault(
+        &self,
+        pt: &mut PageTableImpl,
+        va: usize,
+        _access: AccessType,
+        _attr: &MemoryAttr,
+    ) -> bool {
+        !is_present(pt, va) && swap::swap_in(pt, va, AccessType::Read)
+    }
+}
+
+// 页面已存在时，只有对可写区域的写入需要处理（写时复制等）
+fn is_write_fault(access: AccessType, attr: &MemoryAttr) -> bool {
+    access == AccessType::Write && attr.allows(AccessType::Write)
+}
+
+fn is_present(pt: &mut PageTableImpl, va: usize) -> bool {
+    match pt.get_entry(va) {
+        Some(entry) => entry.present(),
+        None => false,
+    }
+}
+
+// 分配一个清零的页帧并映射到 va，此后该页面可能被换出
+fn lazy_alloc(pt: &mut PageTableImpl, va: usize, attr: &MemoryAttr) -> Result<(), OutOfMemory> {
+    let frame = alloc_frame().ok_or(OutOfMemory)?;
+    unsafe {
+        fill_frame(frame.start_address().as_usize(), 0, 0, 0);
+    }
+    attr.apply(map_or_free(pt, va, frame)?);
+    frame_ref_inc(frame);
+    swap::track(pt, va);
+    Ok(())
+}
+
+// 将 va 映射到新分配的页帧，失败时归还该页帧
+fn map_or_free(
+    pt: &mut PageTableImpl,
+    va: usize,
+    frame: Frame,
+) -> Result<&mut PageEntry, OutOfMemory> {
+    let pa = frame.start_address().as_usize();
+    if pt.map(va, pa).is_err() {
+        dealloc_frame(frame);
+        return Err(OutOfMemory);
+    }
+    Ok(pt.get_entry(va).expect("fail to get an entry!"))
+}
+
+// 撤销一个引用计数页帧的映射，已无人引用时释放该页帧
+fn cow_unmap(pt: &mut PageTableImpl, va: usize) {
+    if is_present(pt, va) {
+        let frame = pt.unmap(va);
+        if frame_ref_dec(frame) == 0 {
+            dealloc_frame(frame);
+        }
+    }
+}
+
+// 让 pt 与 src_pt 以只读方式共享 va 所在的页帧
+fn cow_share(
+    pt: &mut PageTableImpl,
+    src_pt: &mut PageTableImpl,
+    va: usize,
+    attr: &MemoryAttr,
+) -> Result<(), OutOfMemory> {
+    let pa = src_pt.get_entry(va).expect("get src entry error!").target();
+    let entry = pt.map(va, pa)?;
+    attr.apply(entry);
+    entry.set_writable(false);
+    frame_ref_inc(Frame::of_addr(PhysAddr::new(pa)));
+    let src_entry = src_pt.get_entry(va).expect("get src entry error!");
+    src_entry.set_writable(false);
+    src_entry.update();
+    Ok(())
+}
+
+// 处理对写时复制页面的写入，页帧仍被共享时复制一份
+fn cow_write(pt: &mut PageTableImpl, va: usize) -> bool {
+    let entry = match pt.get_entry(va) {
+        Some(entry) if entry.present() && !entry.writable() => entry,
+        _ => return false,
+    };
+    let frame = Frame::of_addr(PhysAddr::new(entry.target()));
+    if frame_ref_count(frame) > 1 {
+        // 仍有其他页表项引用该页帧，复制一份独占的出来
+        let new_frame = match alloc_frame() {
+            Some(frame) => frame,
+            None => return false,
+        };
+        let new_pa = new_frame.start_address().as_usize();
+        unsafe {
+            copy_frame(new_pa, entry.target());
+        }
+        frame_ref_inc(new_frame);
+        frame_ref_dec(frame);
+        entry.set_target(new_pa);
+    }
+    entry.set_writable(true);
+    entry.update();
+    true
+}
+
+// 撤销可换出页面的映射，页面位于交换区时释放其槽位
+fn swap_unmap(pt: &mut PageTableImpl, va: usize) {
+    swap::untrack(pt, va);
+    cow_unmap(pt, va);
+}
+
+// 让 pt 以写时复制的方式共享 src_pt 中的可换出页面，源页面已被换出时先将其换入
+// 尚未分配页帧的页面无需共享
+fn swap_share(
+    pt: &mut PageTableImpl,
+    src_pt: &mut PageTableImpl,
+    va: usize,
+    attr: &MemoryAttr,
+) -> Result<(), OutOfMemory> {
+    if !is_present(src_pt, va) && !swap::is_swapped(src_pt, va) {
+        return Ok(());
+    }
+    swap_ensure(src_pt, va)?;
+    cow_share(pt, src_pt, va, attr)?;
+    swap::track(pt, va);
+    Ok(())
+}
+
+// 确保可换出的页面位于内存中，返回其物理地址
+fn swap_ensure(pt: &mut PageTableImpl, va: usize) -> Result<usize, OutOfMemory> {
+    if !is_present(pt, va) && !swap::swap_in(pt, va, AccessType::Read) {
+        return Err(OutOfMemory);
+    }
+    Ok(pt.get_entry(va).expect("get pa error!").target())
+}
+
+// 为 pt 分配新页帧并复制 src_pt 中同一页面的内容，源页面已被换出时先将其换入
+fn swap_copy(
+    pt: &mut PageTableImpl,
+    src_pt: &mut PageTableImpl,
+    va: usize,
+    attr: &MemoryAttr,
+) -> Result<(), OutOfMemory> {
+    let src_pa = swap_ensure(src_pt, va)?;
+    let frame = alloc_frame().ok_or(OutOfMemory)?;
+    unsafe {
+        copy_frame(frame.start_address().as_usize(), src_pa);
+    }
+    attr.apply(map_or_free(pt, va, frame)?);
+    frame_ref_inc(frame);
+    swap::track(pt, va);
+    Ok(())
+}
+
+// 将 [src, src + length) 复制到物理页 pa 的 offset 处，页内其后的部分清零
+unsafe fn fill_frame(pa: usize, offset: usize, src: usize, length: usize) {
+    let dst = core::slice::from_raw_parts_mut(
+        (access_pa_via_va(pa) + offset) as *mut u8,
+        PAGE_SIZE - offset,
+    );
+    if length > 0 {
+        let src = core::slice::from_raw_parts(src as *const u8, length);
+        dst[..length].copy_from_slice(src);
+    }
+    for byte in dst[length..].iter_mut() {
+        *byte = 0;
+    }
+}
+
+unsafe fn copy_frame(dst_pa: usize, src_pa: usize) {
+    let src = core::slice::from_raw_parts(access_pa_via_va(src_pa) as *const u8, PAGE_SIZE);
+    let dst = core::slice::from_raw_parts_mut(access_pa_via_va(dst_pa) as *mut u8, PAGE_SIZE);
+    dst.copy_from_slice(src);
 }
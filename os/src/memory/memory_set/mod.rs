@@ -5,14 +5,33 @@ pub mod handler;
 use crate::consts::*;
 use crate::memory::access_pa_via_va;
 use crate::memory::paging::PageTableImpl;
-use alloc::{boxed::Box, vec::Vec};
+use crate::memory::{swap, OutOfMemory};
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
 use area::MemoryArea;
-use attr::MemoryAttr;
-use handler::{Linear, MemoryHandler};
+use attr::{AccessType, MemoryAttr};
+use handler::{ByFrameLazy, Linear, MemoryHandler};
+use spin::Mutex;
+
+// 建立内存区域失败的原因
+#[derive(Debug)]
+pub enum MapError {
+    // 与已有的区域重叠
+    Overlap,
+    OutOfMemory,
+}
+
+impl From<OutOfMemory> for MapError {
+    fn from(_: OutOfMemory) -> Self {
+        MapError::OutOfMemory
+    }
+}
 
 pub struct MemorySet {
     areas: Vec<MemoryArea>,
-    page_table: PageTableImpl,
+    page_table: Arc<Mutex<PageTableImpl>>,
+    // 用户堆为 [heap_start, brk)，紧接在 ELF 各段之后
+    heap_start: usize,
+    brk: usize,
 }
 
 impl MemorySet {
@@ -23,34 +42,54 @@ impl MemorySet {
         attr: MemoryAttr,
         handler: impl MemoryHandler,
         data: Option<(usize, usize)>,
-    ) {
+    ) -> Result<(), MapError> {
         assert!(start <= end, "invalid memory area!");
-        assert!(self.test_free_area(start, end), "memory area overlap!");
+        if !self.test_free_area(start, end) {
+            return Err(MapError::Overlap);
+        }
         let area = MemoryArea::new(start, end, Box::new(handler), attr);
-        area.map(&mut self.page_table);
+        let mut page_table = self.page_table.lock();
+        area.map(&mut page_table)?;
         if let Some((src, length)) = data {
-            area.page_copy(&mut self.page_table, src, length);
+            if let Err(err) = area.page_copy(&mut page_table, src, length) {
+                area.unmap(&mut page_table);
+                return Err(err.into());
+            }
         }
+        drop(page_table);
         self.areas.push(area);
+        Ok(())
     }
-    fn test_free_area(&self, start: usize, end: usize) -> bool {
+    // [start, end) 是否与已有的区域都不重叠
+    pub fn test_free_area(&self, start: usize, end: usize) -> bool {
         self.areas
             .iter()
             .find(|area| area.is_overlap_with(start, end))
             .is_none()
     }
     pub unsafe fn activate(&self) {
-        self.page_table.activate();
+        self.page_table.lock().activate();
     }
     pub fn new() -> Self {
+        Self::try_new().expect("failed to create memory set!")
+    }
+    pub fn try_new() -> Result<Self, MapError> {
         let mut memory_set = MemorySet {
             areas: Vec::new(),
-            page_table: PageTableImpl::new_bare(),
+            page_table: Self::new_table()?,
+            heap_start: 0,
+            brk: 0,
         };
-        memory_set.map_kernel_and_physical_memory();
-        memory_set
+        memory_set.map_kernel_and_physical_memory()?;
+        Ok(memory_set)
+    }
+    // 新建一个页表并登记到交换管理中
+    fn new_table() -> Result<Arc<Mutex<PageTableImpl>>, OutOfMemory> {
+        let table = Arc::new(Mutex::new(PageTableImpl::new_bare()?));
+        swap::register_table(&table);
+        Ok(table)
     }
-    pub fn map_kernel_and_physical_memory(&mut self) {
+    pub fn map_kernel_and_physical_memory(&mut self) -> Result<(), MapError> {
         extern "C" {
             fn stext();
             fn etext();
@@ -71,7 +110,7 @@ impl MemorySet {
             MemoryAttr::default().set_readonly().set_execute(),
             Linear::new(offset),
             None,
-        );
+        )?;
         // .rodata R
         self.push(
             srodata as usize,
@@ -79,7 +118,7 @@ impl MemorySet {
             MemoryAttr::default().set_readonly(),
             Linear::new(offset),
             None,
-        );
+        )?;
         // .data R|W
         self.push(
             sdata as usize,
@@ -87,7 +126,7 @@ impl MemorySet {
             MemoryAttr::default(),
             Linear::new(offset),
             None,
-        );
+        )?;
         // .bss R|W
         self.push(
             sbss as usize,
@@ -95,7 +134,7 @@ impl MemorySet {
             MemoryAttr::default(),
             Linear::new(offset),
             None,
-        );
+        )?;
         // 物理内存 R|W
         self.push(
             (end as usize / PAGE_SIZE + 1) * PAGE_SIZE,
@@ -103,9 +142,251 @@ impl MemorySet {
             MemoryAttr::default(),
             Linear::new(offset),
             None,
-        );
+        )
     }
     pub fn token(&self) -> usize {
-        self.page_table.token()
+        self.page_table.lock().token()
+    }
+    #[allow(dead_code)]
+    pub fn get_table(&self) -> Arc<Mutex<PageTableImpl>> {
+        self.page_table.clone()
+    }
+    // 查找 va 所在的区域
+    pub fn find_area(&self, va: usize) -> Option<&MemoryArea> {
+        self.areas.iter().find(|area| area.contains(va))
+    }
+    // 交给 va 所在区域的 handler 处理缺页，返回是否处理成功
+    pub fn handle_page_fault(&mut self, va: usize, access: AccessType) -> bool {
+        match self.areas.iter().find(|area| area.contains(va)) {
+            Some(area) => area.handle_page_fault(&mut self.page_table.lock(), va, access),
+            None => false,
+        }
+    }
+    // 检查 [start, end) 是否完全处于允许该种访问的用户区域中
+    pub fn check_user_range(&self, start: usize, end: usize, access: AccessType) -> bool {
+        let mut va = start;
+        while va < end {
+            match self.find_area(va) {
+                Some(area) if area.user_allows(access) => va = area.page_end(),
+                _ => return false,
+            }
+        }
+        true
+    }
+    // 将用户地址翻译为物理地址，页面尚不可用时先交给 handler 处理（如写时复制）
+    pub fn translate_user(&mut self, va: usize, access: AccessType) -> Option<usize> {
+        let area = self
+            .areas
+            .iter()
+            .find(|area| area.contains(va) && area.user_allows(access))?;
+        let mut page_table = self.page_table.lock();
+        let ready = match page_table.get_entry(va) {
+            Some(entry) => entry.present() && (access != AccessType::Write || entry.writable()),
+            None => false,
+        };
+        if !ready && !area.handle_page_fault(&mut page_table, va, access) {
+            return None;
+        }
+        let entry = page_table.get_entry(va)?;
+        // 内核经由线性映射写入时硬件不会设置脏位
+        if access == AccessType::Write {
+            entry.set_dirty();
+        }
+        Some(entry.target() + va % PAGE_SIZE)
+    }
+    // 在该地址空间与内核缓冲区之间复制数据，该地址空间不必是当前正在使用的
+    // 对于 [va, va + len) 中位于同一页的每一段调用 f(内核可访问的地址, 长度)
+    fn for_each_user_page(
+        &mut self,
+        va: usize,
+        len: usize,
+        access: AccessType,
+        mut f: impl FnMut(usize, usize),
+    ) -> bool {
+        let end = match va.checked_add(len) {
+            Some(end) => end,
+            None => return false,
+        };
+        if !self.check_user_range(va, end, access) {
+            return false;
+        }
+        let mut cur = va;
+        while cur < end {
+            let size = (PAGE_SIZE - cur % PAGE_SIZE).min(end - cur);
+            match self.translate_user(cur, access) {
+                Some(pa) => f(access_pa_via_va(pa), size),
+                None => return false,
+            }
+            cur += size;
+        }
+        true
+    }
+    pub fn copy_from_user(&mut self, dst: &mut [u8], va: usize) -> bool {
+        let mut copied = 0;
+        self.for_each_user_page(va, dst.len(), AccessType::Read, |kva, size| {
+            let src = unsafe { core::slice::from_raw_parts(kva as *const u8, size) };
+            dst[copied..copied + size].copy_from_slice(src);
+            copied += size;
+        })
+    }
+    pub fn copy_to_user(&mut self, va: usize, src: &[u8]) -> bool {
+        let mut copied = 0;
+        self.for_each_user_page(va, src.len(), AccessType::Write, |kva, size| {
+            let dst = unsafe { core::slice::from_raw_parts_mut(kva as *mut u8, size) };
+            dst.copy_from_slice(&src[copied..copied + size]);
+            copied += size;
+        })
+    }
+    // [start, end) 是否与用户堆 [heap_start, brk) 所在的页面重叠
+    pub fn is_heap_overlap(&self, start: usize, end: usize) -> bool {
+        self.brk > self.heap_start && start < page_round_up(self.brk) && end > self.heap_start
+    }
+    // 设置用户堆的起始地址，此时堆为空
+    pub fn set_heap_base(&mut self, base: usize) {
+        self.heap_start = base;
+        self.brk = base;
+    }
+    // 将堆顶调整为 new_brk，返回调整后的堆顶，失败时堆顶保持不变
+    // 堆顶不能超过 USER_MMAP_BASE，以免伸入 mmap 使用的区域
+    pub fn brk(&mut self, new_brk: usize) -> usize {
+        if new_brk >= self.heap_start && new_brk <= USER_MMAP_BASE && self.resize_heap(new_brk) {
+            self.brk = new_brk;
+        }
+        self.brk
+    }
+    fn resize_heap(&mut self, new_brk: usize) -> bool {
+        let old_end = page_round_up(self.brk);
+        let new_end = page_round_up(new_brk);
+        if new_end > old_end && !self.test_free_area(old_end, new_end) {
+            return false;
+        }
+        let heap_start = self.heap_start;
+        match self.areas.iter().position(|area| area.contains(heap_start)) {
+            // 堆原先为空
+            None if new_end > old_end => self
+                .push(
+                    heap_start,
+                    new_end,
+                    MemoryAttr::default().set_user(),
+                    ByFrameLazy::new(),
+                    None,
+                )
+                .is_ok(),
+            None => true,
+            Some(i) if new_end == heap_start => {
+                let area = self.areas.remove(i);
+                area.unmap(&mut self.page_table.lock());
+                true
+            }
+            Some(i) => self.areas[i]
+                .resize(&mut self.page_table.lock(), new_end)
+                .is_ok(),
+        }
+    }
+    // 从 hint 开始向上寻找一段长度为 len 且不超过 limit 的空闲地址，hint 需页对齐
+    pub fn find_free_area(&self, hint: usize, len: usize, limit: usize) -> Option<usize> {
+        let mut start = hint;
+        loop {
+            let end = start.checked_add(len).filter(|&end| end <= limit)?;
+            match self
+                .areas
+                .iter()
+                .find(|area| area.is_overlap_with(start, end))
+            {
+                Some(area) => start = area.page_end(),
+                None => return Some(start),
+            }
+        }
+    }
+    // [start, end) 中的已映射部分是否都属于用户区域
+    pub fn is_user_range(&self, start: usize, end: usize) -> bool {
+        self.areas
+            .iter()
+            .all(|area| !area.is_overlap_with(start, end) || area.is_user())
+    }
+    // 撤销 [start, end) 中的所有映射，跨越边界的区域会被切开，start 与 end 需页对齐
+    pub fn unmap_range(&mut self, start: usize, end: usize) {
+        let removed = self.split_areas(start, end);
+        let mut page_table = self.page_table.lock();
+        for area in removed.iter() {
+            area.unmap(&mut page_table);
+        }
+    }
+    // 将 [start, end) 中文件映射被修改过的页面写回文件
+    pub fn sync_range(&mut self, start: usize, end: usize) {
+        let mut page_table = self.page_table.lock();
+        for area in self.areas.iter() {
+            if area.is_overlap_with(start, end) {
+                area.sync(&mut page_table, start, end);
+            }
+        }
+    }
+    // [start, end) 中的各区域能否都通过 mprotect 加上写权限
+    pub fn allows_write_range(&self, start: usize, end: usize) -> bool {
+        self.areas
+            .iter()
+            .all(|area| !area.is_overlap_with(start, end) || area.allows_write())
+    }
+    // 修改 [start, end) 中所有区域的访问权限，start 与 end 需页对齐
+    pub fn protect_range(&mut self, start: usize, end: usize, attr: MemoryAttr) {
+        let mut protected = self.split_areas(start, end);
+        let mut page_table = self.page_table.lock();
+        for area in protected.iter_mut() {
+            area.protect(&mut page_table, attr.clone());
+        }
+        self.areas.append(&mut protected);
+    }
+    // 将与 [start, end) 重叠的区域切开，取出落在范围内的部分，其余部分保留
+    fn split_areas(&mut self, start: usize, end: usize) -> Vec<MemoryArea> {
+        let mut kept = Vec::new();
+        let mut taken = Vec::new();
+        for area in self.areas.drain(..) {
+            if !area.is_overlap_with(start, end) {
+                kept.push(area);
+                continue;
+            }
+            let (left, middle, right) = area.split(start, end);
+            kept.extend(left);
+            kept.extend(right);
+            taken.push(middle);
+        }
+        self.areas = kept;
+        taken
+    }
+    // 为 fork 复制一份地址空间，各区域如何复制由其 handler 决定
+    pub fn fork(&mut self) -> Result<MemorySet, OutOfMemory> {
+        let mut memory_set = MemorySet {
+            areas: Vec::new(),
+            page_table: Self::new_table()?,
+            heap_start: self.heap_start,
+            brk: self.brk,
+        };
+        {
+            let mut dst = memory_set.page_table.lock();
+            let mut src = self.page_table.lock();
+            for area in self.areas.iter() {
+                area.clone_map(&mut dst, &mut src)?;
+                memory_set.areas.push(area.clone());
+            }
+        }
+        Ok(memory_set)
+    }
+}
+
+// 调用者需保证 addr 不超过 USER_MMAP_BASE，因而不会溢出
+fn page_round_up(addr: usize) -> usize {
+    (addr + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE
+}
+
+// 撤销各区域的映射并释放其占用的页帧，页表随后在 PageTableImpl 中整体回收
+impl Drop for MemorySet {
+    fn drop(&mut self) {
+        let mut page_table = self.page_table.lock();
+        for area in self.areas.iter() {
+            if area.need_unmap() {
+                area.unmap(&mut page_table);
+            }
+        }
+        swap::unregister_table(page_table.token());
     }
 }
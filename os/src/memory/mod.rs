@@ -1,30 +1,80 @@
-mod frame_allocator;
+pub mod frame_allocator;
+pub mod kernel_stack;
 pub mod memory_set;
 pub mod paging;
+pub mod swap;
 
 use crate::consts::*;
 use buddy_system_allocator::LockedHeap;
-use frame_allocator::SEGMENT_TREE_ALLOCATOR as FRAME_ALLOCATOR;
+use frame_allocator::{FrameAllocator, BUDDY_ALLOCATOR, FIRST_FIT_ALLOCATOR, FRAME_REF_COUNTER};
 use memory_set::{attr::MemoryAttr, handler::Linear, MemorySet};
 use riscv::addr::Frame;
-use riscv::register::sstatus;
+use spin::Mutex;
 
-pub fn init(l: usize, r: usize) {
-    unsafe {
-        sstatus::set_sum();
+// 物理页帧耗尽，无法完成分配
+#[derive(Debug)]
+pub struct OutOfMemory;
+
+#[allow(dead_code)]
+enum FrameAllocatorKind {
+    FirstFit,
+    Buddy,
+}
+
+// 在这里选择所使用的物理页帧分配算法
+const FRAME_ALLOCATOR: FrameAllocatorKind = FrameAllocatorKind::FirstFit;
+
+fn frame_allocator() -> &'static Mutex<dyn FrameAllocator> {
+    match FRAME_ALLOCATOR {
+        FrameAllocatorKind::FirstFit => &FIRST_FIT_ALLOCATOR,
+        FrameAllocatorKind::Buddy => &BUDDY_ALLOCATOR,
     }
-    FRAME_ALLOCATOR.lock().init(l, r);
+}
+
+pub fn init(l: usize, r: usize) {
+    init_allocator(l, r);
     init_heap();
+    kernel_stack::init();
     kernel_remap();
     println!("++++ setup memory!    ++++");
 }
 
+pub fn init_allocator(l: usize, r: usize) {
+    frame_allocator().lock().init(l, r);
+}
+
 pub fn alloc_frame() -> Option<Frame> {
-    Some(Frame::of_ppn(FRAME_ALLOCATOR.lock().alloc()))
+    let ppn = frame_allocator().lock().alloc(1);
+    // 物理页帧耗尽时换出一个页面
+    ppn.map(Frame::of_ppn).or_else(swap::swap_out)
 }
 
 pub fn dealloc_frame(f: Frame) {
-    FRAME_ALLOCATOR.lock().dealloc(f.number())
+    dealloc_frames(f, 1)
+}
+
+// 分配 cnt 个物理地址连续的页帧，返回第一个页帧
+// 目前只有 pmm_test 使用
+#[allow(dead_code)]
+pub fn alloc_frames(cnt: usize) -> Option<Frame> {
+    frame_allocator().lock().alloc(cnt).map(Frame::of_ppn)
+}
+
+pub fn dealloc_frames(f: Frame, cnt: usize) {
+    frame_allocator().lock().dealloc(f.number(), cnt)
+}
+
+pub fn frame_ref_count(f: Frame) -> usize {
+    FRAME_REF_COUNTER.lock().get(f.number())
+}
+
+pub fn frame_ref_inc(f: Frame) {
+    FRAME_REF_COUNTER.lock().inc(f.number())
+}
+
+// 返回减少后的引用计数
+pub fn frame_ref_dec(f: Frame) -> usize {
+    FRAME_REF_COUNTER.lock().dec(f.number())
 }
 
 fn init_heap() {
@@ -42,36 +92,45 @@ pub fn access_pa_via_va(pa: usize) -> usize {
 
 pub fn kernel_remap() {
     let mut memory_set = MemorySet::new();
+    // 启动阶段内存不足时无法继续运行
 
     extern "C" {
         fn bootstack();
         fn bootstacktop();
     }
-    memory_set.push(
-        bootstack as usize,
-        bootstacktop as usize,
-        MemoryAttr::default(),
-        Linear::new(PHYSICAL_MEMORY_OFFSET),
-        None,
-    );
-    memory_set.push(
-        access_pa_via_va(0x0c00_0000),
-        access_pa_via_va(0x0c20_2000),
-        MemoryAttr::default(),
-        Linear::new(PHYSICAL_MEMORY_OFFSET),
-        None,
-    );
-    memory_set.push(
-        access_pa_via_va(0x1000_0000),
-        access_pa_via_va(0x1000_1000),
-        MemoryAttr::default(),
-        Linear::new(PHYSICAL_MEMORY_OFFSET),
-        None,
-    );
+    memory_set
+        .push(
+            bootstack as usize,
+            bootstacktop as usize,
+            MemoryAttr::default(),
+            Linear::new(PHYSICAL_MEMORY_OFFSET),
+            None,
+        )
+        .expect("failed to remap kernel!");
+    memory_set
+        .push(
+            access_pa_via_va(0x0c00_0000),
+            access_pa_via_va(0x0c20_2000),
+            MemoryAttr::default(),
+            Linear::new(PHYSICAL_MEMORY_OFFSET),
+            None,
+        )
+        .expect("failed to remap kernel!");
+    memory_set
+        .push(
+            access_pa_via_va(0x1000_0000),
+            access_pa_via_va(0x1000_1000),
+            MemoryAttr::default(),
+            Linear::new(PHYSICAL_MEMORY_OFFSET),
+            None,
+        )
+        .expect("failed to remap kernel!");
 
     unsafe {
         memory_set.activate();
     }
+    // 内核页表会一直被使用，不能被释放
+    core::mem::forget(memory_set);
 }
 
 #[global_allocator]
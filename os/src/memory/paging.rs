@@ -1,10 +1,11 @@
 use crate::consts::*;
-use crate::memory::{access_pa_via_va, alloc_frame, dealloc_frame};
+use crate::memory::{access_pa_via_va, alloc_frame, dealloc_frame, OutOfMemory};
+use core::sync::atomic::{AtomicUsize, Ordering};
 use riscv::addr::*;
 use riscv::asm::{sfence_vma, sfence_vma_all};
 use riscv::paging::{
-    FrameAllocator, FrameDeallocator, Mapper, PageTable as PageTableEntryArray, PageTableEntry,
-    PageTableFlags as EF, Rv39PageTable,
+    FrameAllocator, FrameDeallocator, MapToError, Mapper, PageTable as PageTableEntryArray,
+    PageTableEntry, PageTableFlags as EF, Rv39PageTable,
 };
 use riscv::register::satp;
 
@@ -27,6 +28,9 @@ impl PageEntry {
     pub fn dirty(&self) -> bool {
         self.0.flags().contains(EF::DIRTY)
     }
+    pub fn set_dirty(&mut self) {
+        self.0.flags_mut().insert(EF::DIRTY);
+    }
     pub fn clear_dirty(&mut self) {
         self.0.flags_mut().remove(EF::DIRTY);
     }
@@ -83,6 +87,11 @@ impl FrameDeallocator for FrameAllocatorForPaging {
     }
 }
 
+// 内核栈区域对应的根页表项，所有页表中的这一项都指向同一个二级页表
+const SHARED_ROOT_INDEX: usize = (KERNEL_STACK_REGION >> 30) & 0x1ff;
+// 共享的二级页表的物理地址，为 0 时尚未建立
+static SHARED_TABLE: AtomicUsize = AtomicUsize::new(0);
+
 pub struct PageTableImpl {
     page_table: Rv39PageTable<'static>,
     root_frame: Frame,
@@ -90,34 +99,63 @@ pub struct PageTableImpl {
 }
 
 impl PageTableImpl {
-    pub fn new_bare() -> Self {
-        let frame = alloc_frame().expect("alloc_frame failed!");
+    pub fn new_bare() -> Result<Self, OutOfMemory> {
+        let frame = alloc_frame().ok_or(OutOfMemory)?;
         let paddr = frame.start_address().as_usize();
         let table = unsafe { &mut *(access_pa_via_va(paddr) as *mut PageTableEntryArray) };
         table.zero();
+        let shared = SHARED_TABLE.load(Ordering::Relaxed);
+        if shared != 0 {
+            table[SHARED_ROOT_INDEX].set(Frame::of_addr(PhysAddr::new(shared)), EF::VALID);
+        }
 
-        PageTableImpl {
+        Ok(PageTableImpl {
             page_table: Rv39PageTable::new(table, PHYSICAL_MEMORY_OFFSET),
             root_frame: frame,
             entry: None,
+        })
+    }
+
+    // 建立内核栈区域的页表，此后新建的页表都与它共享该区域的二级页表
+    pub fn new_shared() -> Result<Self, OutOfMemory> {
+        let mut page_table = Self::new_bare()?;
+        let frame = alloc_frame().ok_or(OutOfMemory)?;
+        let paddr = frame.start_address().as_usize();
+        unsafe {
+            (&mut *(access_pa_via_va(paddr) as *mut PageTableEntryArray)).zero();
         }
+        page_table.root_table()[SHARED_ROOT_INDEX].set(frame, EF::VALID);
+        SHARED_TABLE.store(paddr, Ordering::Relaxed);
+        Ok(page_table)
     }
 
-    pub fn map(&mut self, va: usize, pa: usize) -> &mut PageEntry {
+    fn root_table(&mut self) -> &mut PageTableEntryArray {
+        let paddr = self.root_frame.start_address().as_usize();
+        unsafe { &mut *(access_pa_via_va(paddr) as *mut PageTableEntryArray) }
+    }
+
+    // 建立映射，无法为中间级页表分配页帧时失败
+    pub fn map(&mut self, va: usize, pa: usize) -> Result<&mut PageEntry, OutOfMemory> {
         let flags = EF::VALID | EF::READABLE | EF::WRITABLE;
         let page = Page::of_addr(VirtAddr::new(va));
         let frame = Frame::of_addr(PhysAddr::new(pa));
-        self.page_table
+        match self
+            .page_table
             .map_to(page, frame, flags, &mut FrameAllocatorForPaging)
-            .unwrap()
-            .flush();
-        self.get_entry(va).expect("fail to get an entry!")
+        {
+            Ok(flush) => flush.flush(),
+            Err(MapToError::FrameAllocationFailed) => return Err(OutOfMemory),
+            Err(err) => panic!("failed to map {:#x}: {:?}", va, err),
+        }
+        Ok(self.get_entry(va).expect("fail to get an entry!"))
     }
 
-    pub fn unmap(&mut self, va: usize) {
+    // 撤销映射，返回原先映射到的页帧
+    pub fn unmap(&mut self, va: usize) -> Frame {
         let page = Page::of_addr(VirtAddr::new(va));
-        let (_, flush) = self.page_table.unmap(page).unwrap();
+        let (frame, flush) = self.page_table.unmap(page).unwrap();
         flush.flush();
+        frame
     }
 
     pub fn get_entry(&mut self, va: usize) -> Option<&mut PageEntry> {
@@ -159,6 +197,34 @@ impl PageTableImpl {
     }
 }
 
+// 回收各级页表所占的页帧，叶子页表项所指向的页帧由各 handler 负责释放
+impl Drop for PageTableImpl {
+    fn drop(&mut self) {
+        // 共享的二级页表不属于任何一个页表
+        self.root_table()[SHARED_ROOT_INDEX].set_unused();
+        unsafe {
+            free_table(self.root_frame, 2);
+        }
+    }
+}
+
+unsafe fn free_table(frame: Frame, level: usize) {
+    let table =
+        &*(access_pa_via_va(frame.start_address().as_usize()) as *const PageTableEntryArray);
+    if level > 0 {
+        for i in 0..512 {
+            let flags = table[i].flags();
+            // 有效但不可读写执行的页表项指向下一级页表
+            if flags.contains(EF::VALID)
+                && !flags.intersects(EF::READABLE | EF::WRITABLE | EF::EXECUTABLE)
+            {
+                free_table(table[i].frame(), level - 1);
+            }
+        }
+    }
+    dealloc_frame(frame);
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
 pub struct PageRange {
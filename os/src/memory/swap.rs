@@ -0,0 +1,295 @@
+use crate::consts::{PAGE_SIZE, SWAP_SIZE};
+use crate::memory::access_pa_via_va;
+use crate::memory::memory_set::attr::{AccessType, MemoryAttr};
+use crate::memory::paging::PageTableImpl;
+use crate::memory::{alloc_frame, dealloc_frame, frame_ref_count, frame_ref_dec, frame_ref_inc};
+use alloc::{
+    collections::BTreeMap,
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use lazy_static::*;
+use rcore_fs::dev::{self, Device};
+use riscv::addr::{Frame, PhysAddr};
+use riscv::register::satp;
+use spin::Mutex;
+
+// 交换区：目前没有可用的块设备，暂用内核 .bss 中 SWAP_SIZE 大小的一段内存代替
+// 因此换出只是把页面搬到另一处内存，只用于验证页面置换，接入块设备后替换掉即可
+struct SwapArea(Mutex<[u8; SWAP_SIZE]>);
+
+impl Device for SwapArea {
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> dev::Result<usize> {
+        let area = self.0.lock();
+        let len = buf.len().min(area.len() - offset);
+        buf[..len].copy_from_slice(&area[offset..offset + len]);
+        Ok(len)
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> dev::Result<usize> {
+        let mut area = self.0.lock();
+        let len = buf.len().min(area.len() - offset);
+        area[offset..offset + len].copy_from_slice(&buf[..len]);
+        Ok(len)
+    }
+    fn sync(&self) -> dev::Result<()> {
+        Ok(())
+    }
+}
+
+static SWAP_AREA: SwapArea = SwapArea(Mutex::new([0; SWAP_SIZE]));
+
+// 以 (页表 token, 虚拟页地址) 标识一个可换出的页面
+type PageKey = (usize, usize);
+
+#[derive(Clone)]
+enum PageState {
+    // 尚未分配页帧，第一次访问时通过页面置换获得
+    Unallocated(MemoryAttr),
+    // 位于内存中，参与时钟算法的置换
+    Resident,
+    // 已被换出到交换区的某个槽位
+    Swapped(usize),
+}
+
+struct SwapManager {
+    device: &'static dyn Device,
+    // 交换区各槽位是否被占用
+    used: Vec<bool>,
+    // 各页表，供换出其他地址空间中的页面时使用
+    tables: BTreeMap<usize, Weak<Mutex<PageTableImpl>>>,
+    pages: BTreeMap<PageKey, PageState>,
+    // 时钟算法的环形队列及指针
+    clock: Vec<PageKey>,
+    hand: usize,
+}
+
+impl SwapManager {
+    fn new() -> Self {
+        SwapManager {
+            device: &SWAP_AREA,
+            used: vec![false; SWAP_SIZE / PAGE_SIZE],
+            tables: BTreeMap::new(),
+            pages: BTreeMap::new(),
+            clock: Vec::new(),
+            hand: 0,
+        }
+    }
+
+    // 插入到时钟指针之前，转完一圈后才会被检查
+    fn push_clock(&mut self, key: PageKey) {
+        self.clock.insert(self.hand, key);
+        self.hand += 1;
+    }
+
+    fn remove_clock(&mut self, key: PageKey) {
+        if let Some(i) = self.clock.iter().position(|k| *k == key) {
+            self.clock.remove(i);
+            if i < self.hand {
+                self.hand -= 1;
+            }
+        }
+    }
+
+    fn make_resident(&mut self, key: PageKey) {
+        self.pages.insert(key, PageState::Resident);
+        self.push_clock(key);
+    }
+
+    // 按时钟算法选出一个页面换出，返回它原先占用的物理页帧
+    // cur 为调用者已经持有的页表，其余页表被占用时跳过其中的页面
+    fn swap_out(&mut self, mut cur: Option<&mut PageTableImpl>) -> Option<usize> {
+        let slot = self.used.iter().position(|used| !used)?;
+        // 第一圈清除访问位，第二圈内一定能找到可换出的页面
+        let mut budget = self.clock.len() * 2 + 1;
+        while budget > 0 && !self.clock.is_empty() {
+            budget -= 1;
+            if self.hand >= self.clock.len() {
+                self.hand = 0;
+            }
+            let (token, va) = self.clock[self.hand];
+            let victim = match cur {
+                Some(ref mut pt) if pt.token() == token => self.check_victim(pt, va, slot),
+                _ => match self.tables.get(&token).and_then(Weak::upgrade) {
+                    Some(table) => match table.try_lock() {
+                        Some(mut pt) => self.check_victim(&mut pt, va, slot),
+                        None => None,
+                    },
+                    None => None,
+                },
+            };
+            match victim {
+                Some(pa) => {
+                    self.used[slot] = true;
+                    self.pages.insert((token, va), PageState::Swapped(slot));
+                    self.clock.remove(self.hand);
+                    return Some(pa);
+                }
+                None => self.hand += 1,
+            }
+        }
+        None
+    }
+
+    // 最近被访问过的页面获得第二次机会，否则将其写入交换区并标记为不存在
+    // 写时复制共享中的页帧还被其他页表引用，不能换出
+    fn check_victim(&mut self, pt: &mut PageTableImpl, va: usize, slot: usize) -> Option<usize> {
+        let entry = pt.get_entry(va)?;
+        if !entry.present() {
+            return None;
+        }
+        let frame = Frame::of_addr(PhysAddr::new(entry.target()));
+        if frame_ref_count(frame) > 1 {
+            return None;
+        }
+        if entry.accessed() {
+            entry.clear_accessed();
+            entry.update();
+            return None;
+        }
+        let pa = entry.target();
+        let data =
+            unsafe { core::slice::from_raw_parts(access_pa_via_va(pa) as *const u8, PAGE_SIZE) };
+        self.device
+            .write_at(slot * PAGE_SIZE, data)
+            .expect("failed to write swap device!");
+        entry.set_present(false);
+        entry.update();
+        frame_ref_dec(frame);
+        Some(pa)
+    }
+
+    fn swap_in_slot(&mut self, slot: usize, pa: usize) {
+        let data =
+            unsafe { core::slice::from_raw_parts_mut(access_pa_via_va(pa) as *mut u8, PAGE_SIZE) };
+        self.device
+            .read_at(slot * PAGE_SIZE, data)
+            .expect("failed to read swap device!");
+        self.used[slot] = false;
+    }
+}
+
+lazy_static! {
+    static ref SWAP: Mutex<SwapManager> = Mutex::new(SwapManager::new());
+}
+
+// 登记一个页表，使其中的页面可以在其他地址空间缺页时被换出
+pub fn register_table(table: &Arc<Mutex<PageTableImpl>>) {
+    let token = table.lock().token();
+    SWAP.lock().tables.insert(token, Arc::downgrade(table));
+}
+
+pub fn unregister_table(token: usize) {
+    SWAP.lock().tables.remove(&token);
+}
+
+// 登记一个已映射的页面，此后它可能被换出
+pub fn track(pt: &PageTableImpl, va: usize) {
+    SWAP.lock().make_resident((pt.token(), va));
+}
+
+// 登记一个尚未分配页帧的页面，第一次访问时再通过页面置换获得页帧
+pub fn reserve(pt: &PageTableImpl, va: usize, attr: &MemoryAttr) {
+    SWAP.lock()
+        .pages
+        .insert((pt.token(), va), PageState::Unallocated(attr.clone()));
+}
+
+// 页面是否仍未分配页帧
+pub fn is_unallocated(pt: &PageTableImpl, va: usize) -> bool {
+    match SWAP.lock().pages.get(&(pt.token(), va)) {
+        Some(PageState::Unallocated(_)) => true,
+        _ => false,
+    }
+}
+
+// 页面是否已被换出到交换区
+pub fn is_swapped(pt: &PageTableImpl, va: usize) -> bool {
+    match SWAP.lock().pages.get(&(pt.token(), va)) {
+        Some(PageState::Swapped(_)) => true,
+        _ => false,
+    }
+}
+
+// 取消登记，页面位于交换区时释放槽位并清除页表项
+pub fn untrack(pt: &mut PageTableImpl, va: usize) {
+    let key = (pt.token(), va);
+    let mut swap = SWAP.lock();
+    match swap.pages.remove(&key) {
+        Some(PageState::Resident) => swap.remove_clock(key),
+        Some(PageState::Swapped(slot)) => {
+            swap.used[slot] = false;
+            if let Some(entry) = pt.get_entry(va) {
+                entry.0.set_unused();
+                entry.update();
+            }
+        }
+        _ => {}
+    }
+}
+
+// 物理页帧耗尽时换出一个页面，腾出其页帧
+pub fn swap_out() -> Option<Frame> {
+    let pa = SWAP.lock().swap_out(None)?;
+    Some(Frame::of_addr(PhysAddr::new(pa)))
+}
+
+// 将 va 所在的页面换入内存，或为尚未分配的页面置换出一个页帧，返回是否处理成功
+pub fn swap_in(pt: &mut PageTableImpl, va: usize, access: AccessType) -> bool {
+    let va = va / PAGE_SIZE * PAGE_SIZE;
+    let key = (pt.token(), va);
+    let state = SWAP.lock().pages.get(&key).cloned();
+    let pa = match state {
+        Some(PageState::Swapped(slot)) => {
+            let pa = match alloc_frame() {
+                Some(frame) => frame.start_address().as_usize(),
+                None => return false,
+            };
+            SWAP.lock().swap_in_slot(slot, pa);
+            let entry = pt.get_entry(va).expect("get swapped entry error!");
+            entry.set_target(pa);
+            entry.set_present(true);
+            entry.update();
+            pa
+        }
+        Some(PageState::Unallocated(attr)) if attr.allows(access) => {
+            // 优先换出一个页面直接使用其页帧，没有可换出的页面时才向分配器申请
+            let victim = SWAP.lock().swap_out(Some(&mut *pt));
+            let pa = match victim.or_else(|| Some(alloc_frame()?.start_address().as_usize())) {
+                Some(pa) => pa,
+                None => return false,
+            };
+            unsafe {
+                let data =
+                    core::slice::from_raw_parts_mut(access_pa_via_va(pa) as *mut u8, PAGE_SIZE);
+                for byte in data.iter_mut() {
+                    *byte = 0;
+                }
+            }
+            match pt.map(va, pa) {
+                Ok(entry) => attr.apply(entry),
+                Err(_) => {
+                    dealloc_frame(Frame::of_addr(PhysAddr::new(pa)));
+                    return false;
+                }
+            }
+            pa
+        }
+        _ => return false,
+    };
+    frame_ref_inc(Frame::of_addr(PhysAddr::new(pa)));
+    SWAP.lock().make_resident(key);
+    true
+}
+
+// 不属于任何进程的地址空间缺页时，根据当前页表处理可换出的页面
+pub fn handle_page_fault(va: usize, access: AccessType) -> bool {
+    let table = match SWAP.lock().tables.get(&satp::read().bits()) {
+        Some(table) => table.upgrade(),
+        None => None,
+    };
+    match table {
+        Some(table) => swap_in(&mut table.lock(), va, access),
+        None => false,
+    }
+}
@@ -3,10 +3,16 @@ pub mod scheduler;
 pub mod structs;
 pub mod thread_pool;
 
-use crate::fs::{INodeExt, ROOT_INODE};
+use crate::context::TrapFrame;
+use crate::fs::{path, INodeExt};
+use crate::memory::memory_set::{attr::AccessType, MemorySet};
+use crate::syscall::Errno;
 use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
 use processor::Processor;
-use scheduler::RRScheduler;
+use scheduler::{MlfqScheduler, RRScheduler, Scheduler, StrideScheduler};
+use spin::Mutex;
 use structs::Thread;
 use thread_pool::ThreadPool;
 
@@ -15,34 +21,81 @@ pub type ExitCode = usize;
 
 static CPU: Processor = Processor::new();
 
+#[allow(dead_code)]
+enum SchedulerKind {
+    RoundRobin,
+    Stride,
+    Mlfq,
+}
+
+// 在这里选择所使用的调度算法
+const SCHEDULER: SchedulerKind = SchedulerKind::Stride;
+
 pub fn init() {
-    let scheduler = RRScheduler::new(1);
-    let thread_pool = ThreadPool::new(100, Box::new(scheduler));
+    let scheduler: Box<dyn Scheduler> = match SCHEDULER {
+        SchedulerKind::RoundRobin => Box::new(RRScheduler::new(1)),
+        SchedulerKind::Stride => Box::new(StrideScheduler::new(1)),
+        SchedulerKind::Mlfq => Box::new(MlfqScheduler::new(3, 1, 50)),
+    };
+    let thread_pool = ThreadPool::new(100, scheduler);
     let idle = Thread::new_kernel(Processor::idle_main as usize);
     idle.append_initial_arguments([&CPU as *const Processor as usize, 0, 0]);
     CPU.init(idle, Box::new(thread_pool));
 
-    execute("rust/user_shell", None);
+    let init = execute("rust/user_shell").expect("failed to start the initial process!");
+    CPU.set_init(init);
 
     println!("++++ setup process!   ++++");
 }
 
-pub fn execute(path: &str, host_tid: Option<Tid>) -> bool {
-    let find_result = ROOT_INODE.lookup(path);
+pub fn execute(path: &str) -> Option<Tid> {
+    let find_result = path::lookup("/", path);
     match find_result {
         Ok(inode) => {
-            let data = inode.read_as_vec().unwrap();
-            let user_thread = unsafe { Thread::new_user(data.as_slice(), host_tid) };
-            CPU.add_thread(user_thread);
-            true
+            let result = inode
+                .read_as_vec()
+                .map_err(Errno::from)
+                .and_then(|data| unsafe { Thread::new_user(data.as_slice()) });
+            match result {
+                Ok(user_thread) => {
+                    let tid = CPU.add_thread(user_thread);
+                    if tid.is_none() {
+                        println!("failed to execute {}: too many threads", path);
+                    }
+                    tid
+                }
+                Err(err) => {
+                    println!("failed to execute {}: {:?}", path, err);
+                    None
+                }
+            }
         }
         Err(_) => {
             println!("command not found!");
-            false
+            None
         }
     }
 }
 
+// 在当前线程中执行 ELF 文件 data，成功时返回 argc
+pub fn exec(data: &[u8], args: &[String], tf: &mut TrapFrame) -> Result<usize, Errno> {
+    unsafe { current_thread_mut().exec(data, args, tf) }
+}
+
+// 内存不足时返回 ENOMEM，线程数达到上限时返回 EAGAIN
+pub fn fork(tf: &TrapFrame) -> Result<Tid, Errno> {
+    let new_thread = current_thread_mut().fork(tf)?;
+    CPU.add_child(new_thread).ok_or(Errno::EAGAIN)
+}
+
+// 尝试在当前线程的地址空间中处理缺页异常
+pub fn handle_page_fault(va: usize, access: AccessType) -> bool {
+    match CPU.current_vm() {
+        Some(vm) => vm.lock().handle_page_fault(va, access),
+        None => false,
+    }
+}
+
 pub fn tick() {
     CPU.tick();
 }
@@ -59,9 +112,30 @@ pub fn yield_now() {
     CPU.yield_now();
 }
 
+pub fn sched_yield() {
+    CPU.sched_yield();
+}
+
+// 返回被回收的子线程及其退出码
+pub fn wait(pid: Tid) -> Option<(Tid, ExitCode)> {
+    CPU.wait(pid)
+}
+
 pub fn wake_up(tid: Tid) {
     CPU.wake_up(tid);
 }
+pub fn set_priority(priority: usize) {
+    CPU.set_priority(priority);
+}
+
+pub fn current_vm() -> Option<Arc<Mutex<MemorySet>>> {
+    CPU.current_vm()
+}
+
+pub fn current_ppid() -> Option<Tid> {
+    CPU.current_ppid()
+}
+
 pub fn current_tid() -> usize {
     CPU.current_tid()
 }
@@ -1,14 +1,20 @@
 use crate::interrupt::*;
+use crate::memory::memory_set::MemorySet;
 use crate::process::structs::*;
-use crate::process::thread_pool::ThreadPool;
-use crate::process::Tid;
+use crate::process::thread_pool::{ThreadPool, WaitStatus};
+use crate::process::{ExitCode, Tid};
 use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 use core::cell::UnsafeCell;
+use spin::Mutex;
 
 pub struct ProcessorInner {
     pool: Box<ThreadPool>,
     idle: Box<Thread>,
     current: Option<(Tid, Box<Thread>)>,
+    // 初始进程，孤儿进程会被过继给它
+    init: Option<Tid>,
 }
 
 pub struct Processor {
@@ -30,6 +36,7 @@ impl Processor {
                 pool,
                 idle,
                 current: None,
+                init: None,
             });
         }
     }
@@ -41,8 +48,22 @@ impl Processor {
             .expect("Processor is not initialized!")
     }
 
-    pub fn add_thread(&self, thread: Box<Thread>) {
-        self.inner().pool.add(thread);
+    pub fn add_thread(&self, thread: Box<Thread>) -> Option<Tid> {
+        self.inner().pool.add(thread)
+    }
+
+    // 添加一个以当前线程为父线程的新线程，线程池已满时返回 None
+    pub fn add_child(&self, mut thread: Box<Thread>) -> Option<Tid> {
+        let inner = self.inner();
+        let (parent_tid, parent) = inner.current.as_mut().unwrap();
+        thread.parent = Some(*parent_tid);
+        let tid = inner.pool.add(thread)?;
+        parent.children.push(tid);
+        Some(tid)
+    }
+
+    pub fn set_init(&self, tid: Tid) {
+        self.inner().init = Some(tid);
     }
 
     pub fn idle_main(&self) -> ! {
@@ -58,7 +79,17 @@ impl Processor {
                     .switch_to(&mut *inner.current.as_mut().unwrap().1);
 
                 // println!("\n<<<< switch_back to idle in idle_main!");
-                let (tid, thread) = inner.current.take().unwrap();
+                let (tid, mut thread) = inner.current.take().unwrap();
+                if inner.pool.is_exited(tid) {
+                    // 此时已切换回 idle 线程，可以安全地释放其资源
+                    // 关闭文件时可能会唤醒其他线程，因此不能在 retrieve 中进行
+                    thread.release();
+                    if thread.parent.is_none() {
+                        // 没有父线程会回收它，直接释放其 tid
+                        inner.pool.remove(tid);
+                        continue;
+                    }
+                }
                 inner.pool.retrieve(tid, thread);
             } else {
                 enable_and_wfi();
@@ -83,11 +114,20 @@ impl Processor {
         let inner = self.inner();
         let tid = inner.current.as_ref().unwrap().0;
 
-        inner.pool.exit(tid);
+        inner.pool.exit(tid, code);
         println!("thread {} exited, exit code = {}", tid, code);
 
-        if let Some(wait) = inner.current.as_ref().unwrap().1.wait {
-            inner.pool.wakeup(wait);
+        // 初始进程没有父线程，退出后其 tid 会被立即释放，此后孤儿线程不再有父线程
+        if inner.init == Some(tid) {
+            inner.init = None;
+        }
+        let thread = &mut inner.current.as_mut().unwrap().1;
+        inner.pool.reparent(
+            core::mem::replace(&mut thread.children, Vec::new()),
+            inner.init,
+        );
+        if let Some(parent) = thread.parent {
+            inner.pool.wakeup(parent);
         }
 
         inner.current.as_mut().unwrap().1.switch_to(&mut inner.idle);
@@ -123,11 +163,58 @@ impl Processor {
         }
     }
 
+    // 让出 CPU，但线程仍保持就绪状态
+    pub fn sched_yield(&self) {
+        let inner = self.inner();
+        if inner.current.is_some() {
+            let flags = disable_and_store();
+            inner.current.as_mut().unwrap().1.switch_to(&mut inner.idle);
+            restore(flags);
+        }
+    }
+
+    // 等待子线程退出并将其回收，没有符合条件的子线程时返回 None
+    pub fn wait(&self, pid: Tid) -> Option<(Tid, ExitCode)> {
+        let inner = self.inner();
+        let flags = disable_and_store();
+        let ret = loop {
+            let (tid, thread) = inner.current.as_mut().unwrap();
+            let tid = *tid;
+            match inner.pool.reap_child(&thread.children, pid) {
+                WaitStatus::Exited(child, code) => {
+                    thread.children.retain(|&t| t != child);
+                    break Some((child, code));
+                }
+                WaitStatus::NoChild => break None,
+                WaitStatus::Running => {
+                    // 子线程退出时会将当前线程唤醒
+                    inner.pool.threads[tid]
+                        .as_mut()
+                        .expect("thread not existed when waiting")
+                        .status = Status::Sleeping;
+                    inner.current.as_mut().unwrap().1.switch_to(&mut inner.idle);
+                }
+            }
+        };
+        restore(flags);
+        ret
+    }
+
     pub fn wake_up(&self, tid: Tid) {
         let inner = self.inner();
         inner.pool.wakeup(tid);
     }
 
+    pub fn set_priority(&self, priority: usize) {
+        let inner = self.inner();
+        let tid = inner.current.as_ref().unwrap().0;
+        inner.pool.set_priority(tid, priority);
+    }
+
+    pub fn current_ppid(&self) -> Option<Tid> {
+        self.inner().current.as_ref().unwrap().1.parent
+    }
+
     pub fn current_tid(&self) -> usize {
         self.inner().current.as_mut().unwrap().0 as usize
     }
@@ -136,4 +223,15 @@ impl Processor {
     pub fn current_thread_mut(&self) -> &mut Thread {
         self.inner().current.as_mut().unwrap().1.as_mut()
     }
+
+    // 当前正在运行的用户线程的地址空间，处理器未初始化或当前为内核线程时返回 None
+    pub fn current_vm(&self) -> Option<Arc<Mutex<MemorySet>>> {
+        unsafe { &*self.inner.get() }
+            .as_ref()?
+            .current
+            .as_ref()?
+            .1
+            .vm
+            .clone()
+    }
 }
@@ -1,4 +1,5 @@
 use super::Tid;
+use alloc::collections::VecDeque;
 use alloc::vec::Vec;
 
 pub trait Scheduler {
@@ -6,6 +7,7 @@ pub trait Scheduler {
     fn pop(&mut self) -> Option<Tid>;
     fn tick(&mut self) -> bool;
     fn exit(&mut self, tid: Tid);
+    fn set_priority(&mut self, tid: Tid, priority: usize);
 }
 
 #[derive(Default)]
@@ -90,4 +92,215 @@ impl Scheduler for RRScheduler {
             self.current = 0;
         }
     }
+
+    fn set_priority(&mut self, _tid: Tid, _priority: usize) {}
+}
+
+// 每次被调度后 pass 增加 BIG_STRIDE / priority
+const BIG_STRIDE: usize = 1 << 20;
+const DEFAULT_PRIORITY: usize = 16;
+
+#[derive(Default)]
+struct StrideInfo {
+    // 是否在就绪队列中
+    valid: bool,
+    // 是否已参与过调度，首次加入时 pass 从当前最小的 pass 开始
+    present: bool,
+    pass: usize,
+    priority: usize,
+}
+
+pub struct StrideScheduler {
+    threads: Vec<StrideInfo>,
+    max_time: usize,
+    time: usize,
+    current: Option<Tid>,
+    // 最近一次被调度线程的 pass，可认为是就绪线程中最小的 pass
+    min_pass: usize,
+}
+
+impl StrideScheduler {
+    pub fn new(max_time_slice: usize) -> Self {
+        StrideScheduler {
+            threads: Vec::default(),
+            max_time: max_time_slice,
+            time: 0,
+            current: None,
+            min_pass: 0,
+        }
+    }
+
+    fn info(&mut self, tid: Tid) -> &mut StrideInfo {
+        if tid + 1 > self.threads.len() {
+            self.threads.resize_with(tid + 1, Default::default);
+        }
+        let min_pass = self.min_pass;
+        let info = &mut self.threads[tid];
+        if !info.present {
+            info.present = true;
+            info.pass = min_pass;
+            info.priority = DEFAULT_PRIORITY;
+        }
+        info
+    }
+}
+
+impl Scheduler for StrideScheduler {
+    fn push(&mut self, tid: Tid) {
+        let min_pass = self.min_pass;
+        let info = self.info(tid);
+        // 睡眠较久的线程不应凭借过小的 pass 长期独占 CPU
+        if (info.pass.wrapping_sub(min_pass) as isize) < 0 {
+            info.pass = min_pass;
+        }
+        info.valid = true;
+    }
+
+    fn pop(&mut self) -> Option<Tid> {
+        let mut ret: Option<Tid> = None;
+        for (tid, info) in self.threads.iter().enumerate() {
+            if !info.valid {
+                continue;
+            }
+            // pass 可能溢出回绕，比较差值的符号
+            let smaller = match ret {
+                Some(min) => (info.pass.wrapping_sub(self.threads[min].pass) as isize) < 0,
+                None => true,
+            };
+            if smaller {
+                ret = Some(tid);
+            }
+        }
+        if let Some(tid) = ret {
+            let info = &mut self.threads[tid];
+            info.valid = false;
+            self.min_pass = info.pass;
+            info.pass = info.pass.wrapping_add(BIG_STRIDE / info.priority);
+            self.time = self.max_time;
+        }
+        self.current = ret;
+        ret
+    }
+
+    fn tick(&mut self) -> bool {
+        if self.current.is_some() {
+            self.time -= 1;
+            return self.time == 0;
+        }
+        true
+    }
+
+    fn exit(&mut self, tid: Tid) {
+        if self.current == Some(tid) {
+            self.current = None;
+        }
+        if tid < self.threads.len() {
+            self.threads[tid] = StrideInfo::default();
+        }
+    }
+
+    fn set_priority(&mut self, tid: Tid, priority: usize) {
+        // 优先级至少为 1
+        self.info(tid).priority = priority.max(1);
+    }
+}
+
+// 多级反馈队列：第 i 级队列的时间片为 base_time << i
+// 用完时间片的线程降一级，每隔 boost_interval 个时钟周期所有线程回到最高级
+// boost_interval 为 0 时不进行提升
+pub struct MlfqScheduler {
+    queues: Vec<VecDeque<Tid>>,
+    // 每个线程所在的队列级别
+    levels: Vec<usize>,
+    base_time: usize,
+    time: usize,
+    current: Option<Tid>,
+    boost_interval: usize,
+    boost_time: usize,
+}
+
+impl MlfqScheduler {
+    pub fn new(level_count: usize, base_time_slice: usize, boost_interval: usize) -> Self {
+        let mut queues = Vec::new();
+        queues.resize_with(level_count, VecDeque::new);
+        MlfqScheduler {
+            queues,
+            levels: Vec::default(),
+            base_time: base_time_slice,
+            time: 0,
+            current: None,
+            boost_interval,
+            boost_time: boost_interval,
+        }
+    }
+
+    fn level(&mut self, tid: Tid) -> &mut usize {
+        if tid + 1 > self.levels.len() {
+            self.levels.resize(tid + 1, 0);
+        }
+        &mut self.levels[tid]
+    }
+
+    fn boost(&mut self) {
+        for level in self.levels.iter_mut() {
+            *level = 0;
+        }
+        let (top, lower) = self.queues.split_first_mut().unwrap();
+        for queue in lower.iter_mut() {
+            top.append(queue);
+        }
+    }
+}
+
+impl Scheduler for MlfqScheduler {
+    fn push(&mut self, tid: Tid) {
+        let level = *self.level(tid);
+        self.queues[level].push_back(tid);
+    }
+
+    fn pop(&mut self) -> Option<Tid> {
+        let mut ret = None;
+        for queue in self.queues.iter_mut() {
+            if let Some(tid) = queue.pop_front() {
+                ret = Some(tid);
+                break;
+            }
+        }
+        if let Some(tid) = ret {
+            self.time = self.base_time << *self.level(tid);
+        }
+        self.current = ret;
+        ret
+    }
+
+    fn tick(&mut self) -> bool {
+        if self.boost_interval > 0 {
+            self.boost_time -= 1;
+            if self.boost_time == 0 {
+                self.boost_time = self.boost_interval;
+                self.boost();
+            }
+        }
+        if let Some(tid) = self.current {
+            self.time -= 1;
+            if self.time == 0 {
+                // 用完了整个时间片，降低其优先级
+                let max_level = self.queues.len() - 1;
+                let level = self.level(tid);
+                *level = (*level + 1).min(max_level);
+                return true;
+            }
+            return false;
+        }
+        true
+    }
+
+    fn exit(&mut self, tid: Tid) {
+        if self.current == Some(tid) {
+            self.current = None;
+        }
+        *self.level(tid) = 0;
+    }
+
+    fn set_priority(&mut self, _tid: Tid, _priority: usize) {}
 }
@@ -1,11 +1,18 @@
 use super::{ExitCode, Tid};
-use crate::alloc::alloc::{alloc, dealloc, Layout};
 use crate::consts::*;
-use crate::context::Context;
+use crate::context::{Context, TrapFrame};
 use crate::fs::file::File;
-use crate::memory::memory_set::{attr::MemoryAttr, handler::ByFrame, MemorySet};
+use crate::memory::memory_set::{
+    attr::MemoryAttr,
+    handler::{ByFrameCow, ByFrameLazy, Guard},
+    MemorySet,
+};
+use crate::memory::{kernel_stack, OutOfMemory};
+use crate::syscall::Errno;
 use alloc::boxed::Box;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use riscv::register::satp;
 use spin::Mutex;
 use xmas_elf::{
@@ -19,15 +26,18 @@ pub enum Status {
     Ready,
     Running(Tid),
     Sleeping,
-    #[allow(dead_code)]
     Exited(ExitCode),
 }
 
 pub struct Thread {
     pub context: Context,
     pub kstack: KernelStack,
-    pub wait: Option<Tid>,
+    pub parent: Option<Tid>,
+    pub children: Vec<Tid>,
+    pub vm: Option<Arc<Mutex<MemorySet>>>,
     pub ofile: [Option<Arc<Mutex<File>>>; NOFILE],
+    // 当前工作目录，总是规范的绝对路径
+    pub cwd: String,
 }
 
 impl Thread {
@@ -39,12 +49,15 @@ impl Thread {
 
     pub fn new_kernel(entry: usize) -> Box<Thread> {
         unsafe {
-            let kstack_ = KernelStack::new();
+            let kstack_ = KernelStack::new().expect("failed to allocate kernel stack!");
             Box::new(Thread {
                 context: Context::new_kernel_thread(entry, kstack_.top(), satp::read().bits()),
                 kstack: kstack_,
-                wait: None,
+                parent: None,
+                children: Vec::new(),
+                vm: None,
                 ofile: [None; NOFILE],
+                cwd: String::from("/"),
             })
         }
     }
@@ -53,8 +66,11 @@ impl Thread {
         Box::new(Thread {
             context: Context::null(),
             kstack: KernelStack::new_empty(),
-            wait: None,
+            parent: None,
+            children: Vec::new(),
+            vm: None,
             ofile: [None; NOFILE],
+            cwd: String::from("/"),
         })
     }
 
@@ -64,76 +80,156 @@ impl Thread {
         }
     }
 
-    pub unsafe fn new_user(data: &[u8], wait_thread: Option<Tid>) -> Box<Thread> {
-        let elf = ElfFile::new(data).expect("failed to analyse elf!");
+    pub unsafe fn new_user(data: &[u8]) -> Result<Box<Thread>, Errno> {
+        let (vm, entry_addr, ustack_top) = Self::new_user_vm(data)?;
+        let kstack = KernelStack::new()?;
 
-        match elf.header.pt2.type_().as_type() {
-            header::Type::Executable => {
-                // println!("it really a executable!");
-            }
-            header::Type::SharedObject => {
-                panic!("shared object is not supported!");
-            }
-            _ => {
-                panic!("unsupported elf type!");
-            }
+        let mut thread = Thread {
+            context: Context::new_user_thread(entry_addr, ustack_top, kstack.top(), vm.token()),
+            kstack,
+            parent: None,
+            children: Vec::new(),
+            vm: Some(Arc::new(Mutex::new(vm))),
+            ofile: [None; NOFILE],
+            cwd: String::from("/"),
+        };
+        for i in 0..3 {
+            thread.ofile[i] = Some(Arc::new(Mutex::new(File::default())));
+        }
+        Ok(Box::new(thread))
+    }
+
+    // 根据 ELF 文件创建用户地址空间，返回地址空间、入口地址与用户栈顶
+    // 不是可执行的 ELF 文件时返回 ENOEXEC，内存不足时返回 ENOMEM
+    unsafe fn new_user_vm(data: &[u8]) -> Result<(MemorySet, usize, usize), Errno> {
+        let elf = ElfFile::new(data).map_err(|_| Errno::ENOEXEC)?;
+
+        // 不支持动态链接的共享对象
+        if elf.header.pt2.type_().as_type() != header::Type::Executable {
+            return Err(Errno::ENOEXEC);
         }
         let entry_addr = elf.header.pt2.entry_point() as usize;
-        let mut vm = elf.make_memory_set();
+        let mut vm = elf.make_memory_set()?;
 
         let ustack_top = {
             let (ustack_bottom, ustack_top) =
                 (USER_STACK_OFFSET, USER_STACK_OFFSET + USER_STACK_SIZE);
+            // 栈下方留一个保护页，防止栈溢出时写入其他区域
+            vm.push(
+                ustack_bottom - PAGE_SIZE,
+                ustack_bottom,
+                MemoryAttr::default(),
+                Guard::new(),
+                None,
+            )?;
             vm.push(
                 ustack_bottom,
                 ustack_top,
                 MemoryAttr::default().set_user(),
-                ByFrame::new(),
+                ByFrameLazy::new(),
                 None,
-            );
+            )?;
             ustack_top
         };
+        Ok((vm, entry_addr, ustack_top))
+    }
 
-        let kstack = KernelStack::new();
+    // 用新的 ELF 替换当前线程的地址空间，tid 与打开的文件保持不变
+    // 参数被压入新的用户栈，返回时 tf 已指向新程序的入口，返回值为 argc
+    // 失败时原地址空间保持不变
+    pub unsafe fn exec(
+        &mut self,
+        data: &[u8],
+        args: &[String],
+        tf: &mut TrapFrame,
+    ) -> Result<usize, Errno> {
+        let (mut vm, entry_addr, ustack_top) = Self::new_user_vm(data)?;
+        let (sp, argv) = push_args(&mut vm, ustack_top, args)?;
+        vm.activate();
+        // 切换到新的页表后才能释放旧的地址空间
+        let old_vm = self.vm.replace(Arc::new(Mutex::new(vm)));
+        drop(old_vm);
 
-        let mut thread = Thread {
-            context: Context::new_user_thread(entry_addr, ustack_top, kstack.top(), vm.token()),
+        tf.x = [0; 32];
+        tf.x[2] = sp;
+        tf.x[10] = args.len();
+        tf.x[11] = argv;
+        tf.sepc = entry_addr;
+        Ok(args.len())
+    }
+
+    // 复制当前线程：地址空间、打开的文件以及中断帧
+    pub fn fork(&self, tf: &TrapFrame) -> Result<Box<Thread>, OutOfMemory> {
+        let vm = self
+            .vm
+            .as_ref()
+            .expect("kernel thread cannot fork!")
+            .lock()
+            .fork()?;
+        let kstack = KernelStack::new()?;
+        Ok(Box::new(Thread {
+            context: unsafe { Context::new_fork(tf, kstack.top(), vm.token()) },
             kstack,
-            wait: wait_thread,
-            ofile: [None; NOFILE],
-        };
-        for i in 0..3 {
-            thread.ofile[i] = Some(Arc::new(Mutex::new(File::default())));
-        }
-        Box::new(thread)
+            parent: None,
+            children: Vec::new(),
+            vm: Some(Arc::new(Mutex::new(vm))),
+            ofile: self.ofile.clone(),
+            cwd: self.cwd.clone(),
+        }))
     }
 
-    // 分配文件描述符
-    pub fn alloc_fd(&mut self) -> i32 {
-        let mut fd = 0;
-        for i in 0usize..NOFILE {
-            if self.ofile[i].is_none() {
-                fd = i;
-                break;
-            }
-        }
+    // 线程退出后释放其地址空间、打开的文件与内核栈，只留下退出码等待回收
+    pub fn release(&mut self) {
+        self.vm = None;
+        self.ofile = [None; NOFILE];
+        self.kstack = KernelStack::new_empty();
+    }
+
+    // 分配文件描述符，已无空闲的文件描述符时返回 None
+    pub fn alloc_fd(&mut self) -> Option<usize> {
+        let fd = (0..NOFILE).find(|&i| self.ofile[i].is_none())?;
         self.ofile[fd] = Some(Arc::new(Mutex::new(File::default())));
-        fd as i32
+        Some(fd)
     }
     // 回收文件描述符
-    pub fn dealloc_fd(&mut self, fd: i32) {
-        assert!(self.ofile[fd as usize].is_some());
-        self.ofile[fd as usize] = None;
+    pub fn dealloc_fd(&mut self, fd: usize) {
+        assert!(self.ofile[fd].is_some());
+        self.ofile[fd] = None;
     }
 }
 
+// 将参数字符串与以 0 结尾的 argv 指针数组依次压入用户栈，返回新的栈顶与 argv
+// 参数的总长度已由 sys_exec 限制，不会超出用户栈，只可能因内存不足而失败
+fn push_args(
+    vm: &mut MemorySet,
+    ustack_top: usize,
+    args: &[String],
+) -> Result<(usize, usize), OutOfMemory> {
+    let mut sp = ustack_top;
+    let mut argv: Vec<usize> = Vec::with_capacity(args.len() + 1);
+    for arg in args.iter() {
+        sp -= arg.len() + 1;
+        if !vm.copy_to_user(sp, arg.as_bytes()) || !vm.copy_to_user(sp + arg.len(), &[0]) {
+            return Err(OutOfMemory);
+        }
+        argv.push(sp);
+    }
+    argv.push(0);
+    let size = argv.len() * core::mem::size_of::<usize>();
+    // 栈指针需要 16 字节对齐
+    sp = (sp - size) & !0xf;
+    let bytes = unsafe { core::slice::from_raw_parts(argv.as_ptr() as *const u8, size) };
+    if !vm.copy_to_user(sp, bytes) {
+        return Err(OutOfMemory);
+    }
+    Ok((sp, sp))
+}
+
 pub struct KernelStack(usize);
 impl KernelStack {
-    pub fn new() -> Self {
-        let bottom = unsafe {
-            alloc(Layout::from_size_align(KERNEL_STACK_SIZE, KERNEL_STACK_SIZE).unwrap()) as usize
-        };
-        KernelStack(bottom)
+    pub fn new() -> Result<Self, OutOfMemory> {
+        // 内核栈位于内核栈区域中，其下方为不映射的保护页
+        Ok(KernelStack(kernel_stack::alloc()?))
     }
     pub fn new_empty() -> Self {
         KernelStack(0)
@@ -146,43 +242,53 @@ impl KernelStack {
 impl Drop for KernelStack {
     fn drop(&mut self) {
         if self.0 != 0 {
-            unsafe {
-                dealloc(
-                    self.0 as _,
-                    Layout::from_size_align(KERNEL_STACK_SIZE, KERNEL_STACK_SIZE).unwrap(),
-                );
-            }
+            kernel_stack::dealloc(self.0);
         }
     }
 }
 
 trait ElfExt {
-    fn make_memory_set(&self) -> MemorySet;
+    fn make_memory_set(&self) -> Result<MemorySet, Errno>;
 }
 
 impl ElfExt for ElfFile<'_> {
-    fn make_memory_set(&self) -> MemorySet {
-        let mut memory_set = MemorySet::new();
+    fn make_memory_set(&self) -> Result<MemorySet, Errno> {
+        let mut memory_set = MemorySet::try_new()?;
+        let mut heap_base = 0;
         for ph in self.program_iter() {
             if ph.get_type() != Ok(Type::Load) {
                 continue;
             }
             let vaddr = ph.virtual_addr() as usize;
             let mem_size = ph.mem_size() as usize;
-            let data = match ph.get_data(self).unwrap() {
-                SegmentData::Undefined(data) => data,
-                _ => unreachable!(),
+            if mem_size == 0 {
+                continue;
+            }
+            // 各段须位于低半部分的用户地址空间中，且互不重叠
+            let end = vaddr
+                .checked_add(mem_size)
+                .filter(|&end| end <= USER_MMAP_END)
+                .ok_or(Errno::ENOEXEC)?;
+            if !memory_set.test_free_area(vaddr, end) {
+                return Err(Errno::ENOEXEC);
+            }
+            let data = match ph.get_data(self) {
+                Ok(SegmentData::Undefined(data)) if data.len() <= mem_size => data,
+                _ => return Err(Errno::ENOEXEC),
             };
 
             memory_set.push(
                 vaddr,
-                vaddr + mem_size,
+                end,
                 ph.flags().to_attr(),
-                ByFrame::new(),
+                ByFrameCow::new(),
                 Some((data.as_ptr() as usize, data.len())),
-            );
+            )?;
+            heap_base = heap_base.max(end);
         }
-        memory_set
+        // 用户堆从最后一个段之后的第一个整页开始
+        memory_set.set_heap_base((heap_base + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE);
+        Ok(memory_set)
     }
 }
 
@@ -191,7 +297,11 @@ trait ToMemoryAttr {
 }
 impl ToMemoryAttr for Flags {
     fn to_attr(&self) -> MemoryAttr {
+        // 有效的页表项总是可读的，因此不检查 is_read()
         let mut flags = MemoryAttr::default().set_user();
+        if !self.is_write() {
+            flags = flags.set_readonly();
+        }
         if self.is_execute() {
             flags = flags.set_execute();
         }
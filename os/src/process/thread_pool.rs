@@ -1,13 +1,19 @@
 use crate::alloc::{boxed::Box, vec::Vec};
 use crate::process::scheduler::Scheduler;
 use crate::process::structs::*;
-use crate::process::Tid;
+use crate::process::{ExitCode, Tid};
 
 pub struct ThreadInfo {
     pub status: Status,
     pub thread: Option<Box<Thread>>,
 }
 
+pub enum WaitStatus {
+    Exited(Tid, ExitCode),
+    Running,
+    NoChild,
+}
+
 pub struct ThreadPool {
     pub threads: Vec<Option<ThreadInfo>>,
     scheduler: Box<dyn Scheduler>,
@@ -24,22 +30,19 @@ impl ThreadPool {
             scheduler,
         }
     }
-    fn alloc_tid(&self) -> Tid {
-        for (i, info) in self.threads.iter().enumerate() {
-            if info.is_none() {
-                return i;
-            }
-        }
-        panic!("alloc tid failed!");
+    fn alloc_tid(&self) -> Option<Tid> {
+        self.threads.iter().position(|info| info.is_none())
     }
 
-    pub fn add(&mut self, _thread: Box<Thread>) {
-        let tid = self.alloc_tid();
+    // 线程池已满时返回 None
+    pub fn add(&mut self, _thread: Box<Thread>) -> Option<Tid> {
+        let tid = self.alloc_tid()?;
         self.threads[tid] = Some(ThreadInfo {
             status: Status::Ready,
             thread: Some(_thread),
         });
         self.scheduler.push(tid);
+        Some(tid)
     }
 
     pub fn acquire(&mut self) -> Option<(Tid, Box<Thread>)> {
@@ -64,20 +67,91 @@ impl ThreadPool {
         }
     }
 
+    // 释放 tid，之后它可以分配给新的线程
+    pub fn remove(&mut self, tid: Tid) {
+        self.threads[tid] = None;
+    }
+
+    pub fn is_exited(&self, tid: Tid) -> bool {
+        match self.threads[tid].as_ref() {
+            Some(info) => matches!(info.status, Status::Exited(_)),
+            None => false,
+        }
+    }
+
     pub fn tick(&mut self) -> bool {
         self.scheduler.tick()
     }
 
-    pub fn exit(&mut self, tid: Tid) {
-        self.threads[tid] = None;
-        self.scheduler.exit(tid);
+    pub fn set_priority(&mut self, tid: Tid, priority: usize) {
+        self.scheduler.set_priority(tid, priority);
     }
 
-    pub fn wakeup(&mut self, tid: Tid) {
+    // 线程退出后成为僵尸线程，直到被等待它的线程回收
+    pub fn exit(&mut self, tid: Tid, code: ExitCode) {
         let proc = self.threads[tid]
             .as_mut()
-            .expect("thread not exist when waking up");
-        proc.status = Status::Ready;
-        self.scheduler.push(tid);
+            .expect("thread not exist when exiting");
+        proc.status = Status::Exited(code);
+        self.scheduler.exit(tid);
+    }
+
+    pub fn wakeup(&mut self, tid: Tid) {
+        let proc = match self.threads[tid].as_mut() {
+            Some(proc) => proc,
+            None => return,
+        };
+        match proc.status {
+            // 已在就绪队列中或已退出的线程无需唤醒
+            Status::Ready | Status::Exited(_) => {}
+            _ => {
+                proc.status = Status::Ready;
+                self.scheduler.push(tid);
+            }
+        }
+    }
+
+    // 在 children 中查找 pid 对应的线程，pid 为 0 表示任意子线程
+    // 若找到已退出的子线程则将其回收并返回退出码
+    pub fn reap_child(&mut self, children: &[Tid], pid: Tid) -> WaitStatus {
+        let mut status = WaitStatus::NoChild;
+        for &tid in children.iter().filter(|&&tid| pid == 0 || tid == pid) {
+            let info = self.threads[tid].as_ref().expect("child not exist!");
+            if let Status::Exited(code) = info.status {
+                self.threads[tid] = None;
+                return WaitStatus::Exited(tid, code);
+            }
+            status = WaitStatus::Running;
+        }
+        status
+    }
+
+    // 将 children 过继给 parent，若其中有已退出的线程则唤醒 parent 来回收
+    // parent 为 None 时不会再有线程回收它们，已退出的线程直接释放
+    pub fn reparent(&mut self, children: Vec<Tid>, parent: Option<Tid>) {
+        // 已退出的线程不会再回收子线程，视为没有父线程
+        let parent = parent.filter(|&parent| !self.is_exited(parent));
+        let mut has_zombie = false;
+        for &tid in children.iter() {
+            let info = self.threads[tid].as_mut().expect("child not exist!");
+            info.thread.as_mut().expect("child is running!").parent = parent;
+            if let Status::Exited(_) = info.status {
+                has_zombie = true;
+                if parent.is_none() {
+                    self.threads[tid] = None;
+                }
+            }
+        }
+        if let Some(parent) = parent {
+            self.threads[parent]
+                .as_mut()
+                .and_then(|info| info.thread.as_mut())
+                .expect("parent not exist!")
+                .children
+                .extend(children);
+            if has_zombie {
+                self.wakeup(parent);
+            }
+        }
     }
 }
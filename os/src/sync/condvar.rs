@@ -24,4 +24,11 @@ impl Condvar {
         }
         /* yield_now(); */
     }
+
+    pub fn notify_all(&self) {
+        let mut queue = self.wait_queue.lock();
+        while let Some(tid) = queue.pop_front() {
+            wake_up(tid);
+        }
+    }
 }
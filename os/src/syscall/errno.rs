@@ -0,0 +1,85 @@
+use crate::memory::memory_set::MapError;
+use crate::memory::OutOfMemory;
+use rcore_fs::vfs::FsError;
+
+// 系统调用出错时返回 -errno，取值与 Linux 保持一致
+#[allow(dead_code)]
+#[repr(isize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Errno {
+    EPERM = 1,
+    ENOENT = 2,
+    ESRCH = 3,
+    EINTR = 4,
+    EIO = 5,
+    E2BIG = 7,
+    ENOEXEC = 8,
+    EBADF = 9,
+    ECHILD = 10,
+    EAGAIN = 11,
+    ENOMEM = 12,
+    EACCES = 13,
+    EFAULT = 14,
+    EBUSY = 16,
+    EEXIST = 17,
+    EXDEV = 18,
+    ENODEV = 19,
+    ENOTDIR = 20,
+    EISDIR = 21,
+    EINVAL = 22,
+    ENFILE = 23,
+    EMFILE = 24,
+    ENOTTY = 25,
+    EFBIG = 27,
+    ENOSPC = 28,
+    ESPIPE = 29,
+    EROFS = 30,
+    EMLINK = 31,
+    EPIPE = 32,
+    ERANGE = 34,
+    ENAMETOOLONG = 36,
+    ENOSYS = 38,
+    ENOTEMPTY = 39,
+    ELOOP = 40,
+}
+
+impl From<FsError> for Errno {
+    fn from(err: FsError) -> Self {
+        match err {
+            FsError::NotSupported => Errno::ENOSYS,
+            FsError::NotFile => Errno::EISDIR,
+            FsError::IsDir => Errno::EISDIR,
+            FsError::NotDir => Errno::ENOTDIR,
+            FsError::EntryNotFound => Errno::ENOENT,
+            FsError::EntryExist => Errno::EEXIST,
+            FsError::NotSameFs => Errno::EXDEV,
+            FsError::InvalidParam => Errno::EINVAL,
+            FsError::NoDeviceSpace => Errno::ENOSPC,
+            FsError::DirRemoved => Errno::ENOENT,
+            FsError::DirNotEmpty => Errno::ENOTEMPTY,
+            FsError::WrongFs => Errno::EINVAL,
+            FsError::DeviceError => Errno::EIO,
+            FsError::IOCTLError => Errno::EINVAL,
+            FsError::NoDevice => Errno::ENODEV,
+            FsError::Again => Errno::EAGAIN,
+            FsError::SymLoop => Errno::ELOOP,
+            FsError::Busy => Errno::EBUSY,
+            FsError::Interrupted => Errno::EINTR,
+        }
+    }
+}
+
+impl From<OutOfMemory> for Errno {
+    fn from(_: OutOfMemory) -> Self {
+        Errno::ENOMEM
+    }
+}
+
+impl From<MapError> for Errno {
+    fn from(err: MapError) -> Self {
+        match err {
+            MapError::Overlap => Errno::EINVAL,
+            MapError::OutOfMemory => Errno::ENOMEM,
+        }
+    }
+}
@@ -0,0 +1,505 @@
+use super::uaccess::*;
+use super::{Errno, SysResult};
+use crate::consts::PAGE_SIZE;
+use crate::fs::file::{File, FileDescriptorType, O_ACCMODE, O_CREAT, O_RDONLY, O_TRUNC};
+use crate::fs::path;
+use crate::fs::pipe::Pipe;
+use crate::memory::memory_set::attr::AccessType;
+use crate::process;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use rcore_fs::vfs::{FileType, FsError, INode, Metadata};
+use spin::Mutex;
+
+// 取出当前线程 fd 对应的文件
+pub fn get_file(fd: usize) -> Result<Arc<Mutex<File>>, Errno> {
+    let thread = process::current_thread_mut();
+    match thread.ofile.get(fd) {
+        Some(Some(file)) => Ok(file.clone()),
+        _ => Err(Errno::EBADF),
+    }
+}
+
+// 当前线程的工作目录
+pub fn current_cwd() -> String {
+    process::current_thread_mut().cwd.clone()
+}
+
+// 带有 O_CREAT 时创建不存在的文件，带有 O_TRUNC 且可写时将文件清空
+pub fn sys_open(path: usize, flags: i32) -> SysResult {
+    let path = copy_cstr_from_user(path)?;
+    let cwd = current_cwd();
+    let inode = match path::lookup(&cwd, &path) {
+        Ok(inode) => inode,
+        Err(FsError::EntryNotFound) if flags & O_CREAT != 0 => {
+            let (dir, name) = path::lookup_parent(&cwd, &path)?;
+            dir.create(&name, FileType::File, 0o666)?
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if flags & O_TRUNC != 0 && flags & O_ACCMODE != O_RDONLY {
+        inode.resize(0)?;
+    }
+    let thread = process::current_thread_mut();
+    let fd = thread.alloc_fd().ok_or(Errno::EMFILE)?;
+    thread.ofile[fd]
+        .as_ref()
+        .unwrap()
+        .lock()
+        .open_file(inode, flags);
+    Ok(fd)
+}
+
+pub fn sys_close(fd: usize) -> SysResult {
+    get_file(fd)?;
+    process::current_thread_mut().dealloc_fd(fd);
+    Ok(0)
+}
+
+// 用户缓冲区按页大小分段经由内核缓冲区复制
+pub fn sys_read(fd: usize, base: usize, len: usize) -> SysResult {
+    check_user_range(base, len, AccessType::Write)?;
+    if fd == 0 {
+        // 如果是标准输入
+        if len == 0 {
+            return Ok(0);
+        }
+        let ch = crate::fs::stdio::STDIN.pop() as u8;
+        write_user(base, &ch)?;
+        return Ok(1);
+    }
+    let file = get_file(fd)?;
+    let mut file = file.lock();
+    if !file.get_readable() {
+        return Err(Errno::EBADF);
+    }
+    match file.get_fdtype() {
+        FileDescriptorType::FdInode => {
+            let inode = file.inode.clone().unwrap();
+            let offset = file.get_offset();
+            let total = read_inode(&inode, offset, base, len)?;
+            file.set_offset(offset + total);
+            Ok(total)
+        }
+        FileDescriptorType::FdPipe => {
+            // 读管道可能阻塞，需先释放文件的锁
+            let pipe = file.pipe.as_ref().unwrap().pipe.clone();
+            drop(file);
+            let mut buf = [0u8; PAGE_SIZE];
+            let s = pipe.read(&mut buf[..len.min(PAGE_SIZE)]);
+            copy_to_user(base, &buf[..s])?;
+            Ok(s)
+        }
+        _ => Err(Errno::EINVAL),
+    }
+}
+
+pub fn sys_write(fd: usize, base: usize, len: usize) -> SysResult {
+    check_user_range(base, len, AccessType::Read)?;
+    let mut buf = [0u8; PAGE_SIZE];
+    if fd == 1 {
+        // 如果是标准输出
+        let mut total = 0;
+        while total < len {
+            let size = (len - total).min(PAGE_SIZE);
+            copy_from_user(&mut buf[..size], base + total)?;
+            for &ch in buf[..size].iter() {
+                crate::io::putchar(ch as char);
+            }
+            total += size;
+        }
+        return Ok(len);
+    }
+    let file = get_file(fd)?;
+    let mut file = file.lock();
+    if !file.get_writable() {
+        return Err(Errno::EBADF);
+    }
+    match file.get_fdtype() {
+        FileDescriptorType::FdInode => {
+            let inode = file.inode.clone().unwrap();
+            let offset = if file.get_append() {
+                inode.metadata()?.size
+            } else {
+                file.get_offset()
+            };
+            let total = write_inode(&inode, offset, base, len)?;
+            file.set_offset(offset + total);
+            Ok(total)
+        }
+        FileDescriptorType::FdPipe => {
+            let pipe = file.pipe.as_ref().unwrap().pipe.clone();
+            drop(file);
+            let mut total = 0;
+            while total < len {
+                let size = (len - total).min(PAGE_SIZE);
+                copy_from_user(&mut buf[..size], base + total)?;
+                let s = pipe.write(&buf[..size]);
+                total += s;
+                if s < size {
+                    // 读端已全部关闭，一个字节都没有写入时才报告 EPIPE
+                    if total == 0 {
+                        return Err(Errno::EPIPE);
+                    }
+                    break;
+                }
+            }
+            Ok(total)
+        }
+        _ => Err(Errno::EINVAL),
+    }
+}
+
+// unlink 的标志，置位时删除的是空目录
+pub const AT_REMOVEDIR: usize = 0x200;
+
+// linux_dirent64 中 d_name 之前的部分：d_ino、d_off、d_reclen 与 d_type
+const DIRENT_HEADER_SIZE: usize = 19;
+
+// 目录项的类型，与 Linux 的 d_type 保持一致
+const DT_FIFO: u8 = 1;
+const DT_CHR: u8 = 2;
+const DT_DIR: u8 = 4;
+const DT_BLK: u8 = 6;
+const DT_REG: u8 = 8;
+const DT_LNK: u8 = 10;
+const DT_SOCK: u8 = 12;
+
+pub fn sys_mkdir(path: usize) -> SysResult {
+    let path = copy_cstr_from_user(path)?;
+    let (dir, name) = path::lookup_parent(&current_cwd(), &path)?;
+    dir.create(&name, FileType::Dir, 0o777)?;
+    Ok(0)
+}
+
+// flags 含有 AT_REMOVEDIR 时删除空目录，否则删除目录以外的文件
+pub fn sys_unlink(path: usize, flags: usize) -> SysResult {
+    let path = copy_cstr_from_user(path)?;
+    let (dir, name) = path::lookup_parent(&current_cwd(), &path)?;
+    let is_dir = dir.find(&name)?.metadata()?.type_ == FileType::Dir;
+    match (is_dir, flags & AT_REMOVEDIR != 0) {
+        (true, false) => return Err(Errno::EISDIR),
+        (false, true) => return Err(Errno::ENOTDIR),
+        _ => {}
+    }
+    dir.unlink(&name)?;
+    Ok(0)
+}
+
+// 目标已存在时由文件系统决定是否失败
+pub fn sys_rename(old_path: usize, new_path: usize) -> SysResult {
+    let old_path = copy_cstr_from_user(old_path)?;
+    let new_path = copy_cstr_from_user(new_path)?;
+    let cwd = current_cwd();
+    let (old_dir, old_name) = path::lookup_parent(&cwd, &old_path)?;
+    let (new_dir, new_name) = path::lookup_parent(&cwd, &new_path)?;
+    old_dir.move_(&old_name, &new_dir, &new_name)?;
+    Ok(0)
+}
+
+// 以 linux_dirent64 的格式从目录的当前位置读出尽可能多的目录项，返回写入的字节数
+// 目录文件的偏移即下一个目录项的序号，读完所有目录项后返回 0
+pub fn sys_getdents(fd: usize, buf: usize, len: usize) -> SysResult {
+    check_user_range(buf, len, AccessType::Write)?;
+    let file = get_file(fd)?;
+    let mut file = file.lock();
+    let inode = match file.get_fdtype() {
+        FileDescriptorType::FdInode => file.inode.clone().unwrap(),
+        _ => return Err(Errno::ENOTDIR),
+    };
+    if inode.metadata()?.type_ != FileType::Dir {
+        return Err(Errno::ENOTDIR);
+    }
+    let mut data = Vec::new();
+    let mut index = file.get_offset();
+    loop {
+        let name = match inode.get_entry(index) {
+            Ok(name) => name,
+            Err(FsError::EntryNotFound) => break,
+            Err(err) => return Err(err.into()),
+        };
+        // 每个目录项按 8 字节对齐
+        let reclen = (DIRENT_HEADER_SIZE + name.len() + 1 + 7) / 8 * 8;
+        if data.len() + reclen > len {
+            // 缓冲区连一个目录项都放不下
+            if data.is_empty() {
+                return Err(Errno::EINVAL);
+            }
+            break;
+        }
+        let metadata = inode.find(&name)?.metadata()?;
+        let start = data.len();
+        data.extend_from_slice(&(metadata.inode as u64).to_le_bytes());
+        data.extend_from_slice(&((index + 1) as i64).to_le_bytes());
+        data.extend_from_slice(&(reclen as u16).to_le_bytes());
+        data.push(dirent_type(metadata.type_));
+        data.extend_from_slice(name.as_bytes());
+        data.resize(start + reclen, 0);
+        index += 1;
+    }
+    copy_to_user(buf, &data)?;
+    file.set_offset(index);
+    Ok(data.len())
+}
+
+fn dirent_type(type_: FileType) -> u8 {
+    match type_ {
+        FileType::File => DT_REG,
+        FileType::Dir => DT_DIR,
+        FileType::SymLink => DT_LNK,
+        FileType::CharDevice => DT_CHR,
+        FileType::BlockDevice => DT_BLK,
+        FileType::NamedPipe => DT_FIFO,
+        FileType::Socket => DT_SOCK,
+    }
+}
+
+// 文件类型在 st_mode 中的取值，与 Linux 保持一致
+const S_IFIFO: u32 = 0o010000;
+const S_IFCHR: u32 = 0o020000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFBLK: u32 = 0o060000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFSOCK: u32 = 0o140000;
+
+// stat 与 fstat 写入用户空间的结构，布局与 Linux 在 RISC-V 上的 struct stat 一致
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Stat {
+    dev: u64,
+    ino: u64,
+    mode: u32,
+    nlink: u32,
+    uid: u32,
+    gid: u32,
+    rdev: u64,
+    _pad1: u64,
+    size: i64,
+    blksize: i32,
+    _pad2: i32,
+    blocks: i64,
+    atime: i64,
+    atime_nsec: u64,
+    mtime: i64,
+    mtime_nsec: u64,
+    ctime: i64,
+    ctime_nsec: u64,
+    _unused: [u32; 2],
+}
+
+impl From<Metadata> for Stat {
+    fn from(info: Metadata) -> Self {
+        Stat {
+            dev: info.dev as u64,
+            ino: info.inode as u64,
+            mode: type_mode(info.type_) | (info.mode as u32 & 0o7777),
+            nlink: info.nlinks as u32,
+            uid: info.uid as u32,
+            gid: info.gid as u32,
+            rdev: info.rdev as u64,
+            size: info.size as i64,
+            blksize: info.blk_size as i32,
+            blocks: info.blocks as i64,
+            atime: info.atime.sec,
+            atime_nsec: info.atime.nsec as u64,
+            mtime: info.mtime.sec,
+            mtime_nsec: info.mtime.nsec as u64,
+            ctime: info.ctime.sec,
+            ctime_nsec: info.ctime.nsec as u64,
+            ..Stat::default()
+        }
+    }
+}
+
+fn type_mode(type_: FileType) -> u32 {
+    match type_ {
+        FileType::File => S_IFREG,
+        FileType::Dir => S_IFDIR,
+        FileType::SymLink => S_IFLNK,
+        FileType::CharDevice => S_IFCHR,
+        FileType::BlockDevice => S_IFBLK,
+        FileType::NamedPipe => S_IFIFO,
+        FileType::Socket => S_IFSOCK,
+    }
+}
+
+pub fn sys_stat(path: usize, buf: usize) -> SysResult {
+    let path = copy_cstr_from_user(path)?;
+    let inode = path::lookup(&current_cwd(), &path)?;
+    write_user(buf, &Stat::from(inode.metadata()?))?;
+    Ok(0)
+}
+
+// 管道与标准输入输出没有对应的 inode，只填写文件类型
+pub fn sys_fstat(fd: usize, buf: usize) -> SysResult {
+    let file = get_file(fd)?;
+    let file = file.lock();
+    let stat = match file.get_fdtype() {
+        FileDescriptorType::FdInode => Stat::from(file.inode.as_ref().unwrap().metadata()?),
+        FileDescriptorType::FdPipe => Stat {
+            mode: S_IFIFO | 0o600,
+            ..Stat::default()
+        },
+        _ => Stat {
+            mode: S_IFCHR | 0o620,
+            ..Stat::default()
+        },
+    };
+    drop(file);
+    write_user(buf, &stat)?;
+    Ok(0)
+}
+
+pub fn sys_chdir(path: usize) -> SysResult {
+    let path = copy_cstr_from_user(path)?;
+    let (cwd, inode) = path::canonicalize(&current_cwd(), &path)?;
+    if inode.metadata()?.type_ != FileType::Dir {
+        return Err(Errno::ENOTDIR);
+    }
+    process::current_thread_mut().cwd = cwd;
+    Ok(0)
+}
+
+// 将以 '\0' 结尾的工作目录写入 buf，返回写入的长度
+pub fn sys_getcwd(buf: usize, size: usize) -> SysResult {
+    let mut cwd = current_cwd();
+    cwd.push('\0');
+    if cwd.len() > size {
+        return Err(Errno::ERANGE);
+    }
+    copy_to_user(buf, cwd.as_bytes())?;
+    Ok(cwd.len())
+}
+
+// 从文件的 offset 处读出至多 len 字节到用户缓冲区 base，返回读到的字节数
+fn read_inode(inode: &Arc<dyn INode>, offset: usize, base: usize, len: usize) -> SysResult {
+    check_offset(offset, len)?;
+    let mut buf = [0u8; PAGE_SIZE];
+    let mut total = 0;
+    while total < len {
+        let size = (len - total).min(PAGE_SIZE);
+        let s = inode.read_at(offset + total, &mut buf[..size])?;
+        copy_to_user(base + total, &buf[..s])?;
+        total += s;
+        if s < size {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+// 将用户缓冲区 base 中的 len 字节写到文件的 offset 处，返回写入的字节数
+fn write_inode(inode: &Arc<dyn INode>, offset: usize, base: usize, len: usize) -> SysResult {
+    check_offset(offset, len)?;
+    let mut buf = [0u8; PAGE_SIZE];
+    let mut total = 0;
+    while total < len {
+        let size = (len - total).min(PAGE_SIZE);
+        copy_from_user(&mut buf[..size], base + total)?;
+        let s = inode.write_at(offset + total, &buf[..size])?;
+        total += s;
+        if s < size {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+// 文件偏移不能超过 isize::MAX，[offset, offset + len) 的末尾也不能超过
+fn check_offset(offset: usize, len: usize) -> Result<(), Errno> {
+    match offset.checked_add(len) {
+        Some(end) if end <= isize::MAX as usize => Ok(()),
+        _ => Err(Errno::EINVAL),
+    }
+}
+
+// lseek 的 whence
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
+// offset 按有符号数解释，允许移动到文件末尾之后，返回新的偏移
+pub fn sys_lseek(fd: usize, offset: usize, whence: usize) -> SysResult {
+    let file = get_file(fd)?;
+    let mut file = file.lock();
+    let inode = match file.get_fdtype() {
+        FileDescriptorType::FdInode => file.inode.clone().unwrap(),
+        _ => return Err(Errno::ESPIPE),
+    };
+    let origin = match whence {
+        SEEK_SET => 0,
+        SEEK_CUR => file.get_offset(),
+        SEEK_END => inode.metadata()?.size,
+        _ => return Err(Errno::EINVAL),
+    };
+    let new_offset = if (offset as isize) < 0 {
+        origin.checked_sub(offset.wrapping_neg())
+    } else {
+        origin.checked_add(offset)
+    }
+    .ok_or(Errno::EINVAL)?;
+    check_offset(new_offset, 0)?;
+    file.set_offset(new_offset);
+    Ok(new_offset)
+}
+
+// 取出可以按位置读写的文件，管道等没有位置的文件返回 ESPIPE
+fn positional_inode(fd: usize, write: bool) -> Result<Arc<dyn INode>, Errno> {
+    let file = get_file(fd)?;
+    let file = file.lock();
+    let allowed = if write {
+        file.get_writable()
+    } else {
+        file.get_readable()
+    };
+    if !allowed {
+        return Err(Errno::EBADF);
+    }
+    match file.get_fdtype() {
+        FileDescriptorType::FdInode => Ok(file.inode.clone().unwrap()),
+        _ => Err(Errno::ESPIPE),
+    }
+}
+
+// 从 offset 处读取，不改变文件的偏移
+pub fn sys_pread(fd: usize, base: usize, len: usize, offset: usize) -> SysResult {
+    check_user_range(base, len, AccessType::Write)?;
+    let inode = positional_inode(fd, false)?;
+    read_inode(&inode, offset, base, len)
+}
+
+// 写到 offset 处，不改变文件的偏移，也不受 O_APPEND 影响
+pub fn sys_pwrite(fd: usize, base: usize, len: usize, offset: usize) -> SysResult {
+    check_user_range(base, len, AccessType::Read)?;
+    let inode = positional_inode(fd, true)?;
+    write_inode(&inode, offset, base, len)
+}
+
+// 创建管道，fds[0] 为读端，fds[1] 为写端
+pub fn sys_pipe(fds: usize) -> SysResult {
+    check_user_range(fds, core::mem::size_of::<[i32; 2]>(), AccessType::Write)?;
+    let thread = process::current_thread_mut();
+    let (read_end, write_end) = Pipe::new_pair();
+    let read_fd = thread.alloc_fd().ok_or(Errno::EMFILE)?;
+    let write_fd = match thread.alloc_fd() {
+        Some(fd) => fd,
+        None => {
+            thread.dealloc_fd(read_fd);
+            return Err(Errno::EMFILE);
+        }
+    };
+    thread.ofile[read_fd]
+        .as_ref()
+        .unwrap()
+        .lock()
+        .open_pipe(read_end);
+    thread.ofile[write_fd]
+        .as_ref()
+        .unwrap()
+        .lock()
+        .open_pipe(write_end);
+    write_user(fds, &[read_fd as i32, write_fd as i32])?;
+    Ok(0)
+}
@@ -0,0 +1,177 @@
+use super::fs::get_file;
+use super::{Errno, SysResult};
+use crate::consts::{PAGE_SIZE, USER_MMAP_BASE, USER_MMAP_END};
+use crate::fs::file::FileDescriptorType;
+use crate::memory::memory_set::{
+    attr::{AccessType, MemoryAttr},
+    handler::{ByFile, ByFrameLazy},
+};
+use crate::process;
+use alloc::sync::Arc;
+use rcore_fs::vfs::INode;
+
+pub const PROT_WRITE: usize = 2;
+pub const PROT_EXEC: usize = 4;
+
+pub const MAP_SHARED: usize = 0x1;
+pub const MAP_PRIVATE: usize = 0x2;
+pub const MAP_FIXED: usize = 0x10;
+pub const MAP_ANONYMOUS: usize = 0x20;
+
+pub const MS_ASYNC: usize = 1;
+pub const MS_INVALIDATE: usize = 2;
+pub const MS_SYNC: usize = 4;
+
+// 将堆顶设为 addr，返回新的堆顶；失败或 addr 为 0 时返回当前的堆顶
+pub fn sys_brk(addr: usize) -> SysResult {
+    let vm = process::current_vm().ok_or(Errno::ENOMEM)?;
+    let brk = vm.lock().brk(addr);
+    Ok(brk)
+}
+
+// 文件映射的页面在第一次访问时才从文件读入，匿名映射只支持私有的
+// 未指定 MAP_FIXED 时 addr 仅作为参考，返回实际映射的起始地址
+pub fn sys_mmap(
+    addr: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: usize,
+    offset: usize,
+) -> SysResult {
+    let shared = match flags & (MAP_SHARED | MAP_PRIVATE) {
+        MAP_SHARED => true,
+        MAP_PRIVATE => false,
+        _ => return Err(Errno::EINVAL),
+    };
+    let inode = if flags & MAP_ANONYMOUS != 0 {
+        if shared {
+            return Err(Errno::EINVAL);
+        }
+        None
+    } else {
+        if offset % PAGE_SIZE != 0 {
+            return Err(Errno::EINVAL);
+        }
+        Some(mappable_inode(fd, prot, shared)?)
+    };
+    let (start, end) = check_range(addr, len)?;
+    let len = end - start;
+    let vm = process::current_vm().ok_or(Errno::ENOMEM)?;
+    let mut vm = vm.lock();
+    let start = if flags & MAP_FIXED != 0 {
+        if end > USER_MMAP_END || !vm.is_user_range(start, end) || vm.is_heap_overlap(start, end) {
+            return Err(Errno::EINVAL);
+        }
+        vm.unmap_range(start, end);
+        start
+    } else {
+        let hint = if start >= USER_MMAP_BASE {
+            start
+        } else {
+            USER_MMAP_BASE
+        };
+        vm.find_free_area(hint, len, USER_MMAP_END)
+            .or_else(|| vm.find_free_area(USER_MMAP_BASE, len, USER_MMAP_END))
+            .ok_or(Errno::ENOMEM)?
+    };
+    let attr = prot_to_attr(prot);
+    match inode {
+        Some((inode, writable)) => {
+            let handler = ByFile::new(inode, start, offset, shared, writable);
+            vm.push(start, start + len, attr, handler, None)?
+        }
+        None => vm.push(start, start + len, attr, ByFrameLazy::new(), None)?,
+    }
+    Ok(start)
+}
+
+// 撤销 [addr, addr + len) 中的映射，其中没有映射的部分被忽略
+// 用户堆只能通过 brk 调整
+pub fn sys_munmap(addr: usize, len: usize) -> SysResult {
+    let (start, end) = check_range(addr, len)?;
+    let vm = process::current_vm().ok_or(Errno::ENOMEM)?;
+    let mut vm = vm.lock();
+    if !vm.is_user_range(start, end) || vm.is_heap_overlap(start, end) {
+        return Err(Errno::EINVAL);
+    }
+    vm.unmap_range(start, end);
+    Ok(0)
+}
+
+// [addr, addr + len) 必须全部已被映射，且不能包含用户堆
+// 以只读方式打开的文件的共享映射不能加上写权限
+pub fn sys_mprotect(addr: usize, len: usize, prot: usize) -> SysResult {
+    let (start, end) = check_range(addr, len)?;
+    let vm = process::current_vm().ok_or(Errno::ENOMEM)?;
+    let mut vm = vm.lock();
+    if !vm.is_user_range(start, end) || vm.is_heap_overlap(start, end) {
+        return Err(Errno::EINVAL);
+    }
+    if !vm.check_user_range(start, end, AccessType::Read) {
+        return Err(Errno::ENOMEM);
+    }
+    if prot & PROT_WRITE != 0 && !vm.allows_write_range(start, end) {
+        return Err(Errno::EACCES);
+    }
+    vm.protect_range(start, end, prot_to_attr(prot));
+    Ok(0)
+}
+
+// 将 [addr, addr + len) 中共享文件映射被修改过的页面写回文件，总是同步完成
+pub fn sys_msync(addr: usize, len: usize, flags: usize) -> SysResult {
+    if flags & !(MS_ASYNC | MS_INVALIDATE | MS_SYNC) != 0
+        || flags & (MS_ASYNC | MS_SYNC) == MS_ASYNC | MS_SYNC
+    {
+        return Err(Errno::EINVAL);
+    }
+    let (start, end) = check_range(addr, len)?;
+    let vm = process::current_vm().ok_or(Errno::ENOMEM)?;
+    let mut vm = vm.lock();
+    if !vm.check_user_range(start, end, AccessType::Read) {
+        return Err(Errno::ENOMEM);
+    }
+    vm.sync_range(start, end);
+    Ok(0)
+}
+
+// 只有普通文件可以映射，共享的可写映射还要求文件以可写方式打开
+// 返回文件的 INode 及其是否可写
+fn mappable_inode(fd: usize, prot: usize, shared: bool) -> Result<(Arc<dyn INode>, bool), Errno> {
+    let file = get_file(fd)?;
+    let file = file.lock();
+    let inode = match file.get_fdtype() {
+        FileDescriptorType::FdInode => file.inode.clone().unwrap(),
+        _ => return Err(Errno::ENODEV),
+    };
+    if !file.get_readable() || (shared && prot & PROT_WRITE != 0 && !file.get_writable()) {
+        return Err(Errno::EACCES);
+    }
+    Ok((inode, file.get_writable()))
+}
+
+// addr 需页对齐，len 向上取整到页大小，返回 [start, end)
+fn check_range(addr: usize, len: usize) -> Result<(usize, usize), Errno> {
+    if addr % PAGE_SIZE != 0 || len == 0 {
+        return Err(Errno::EINVAL);
+    }
+    let end = addr
+        .checked_add(len)
+        .and_then(|end| end.checked_add(PAGE_SIZE - 1))
+        .ok_or(Errno::EINVAL)?
+        / PAGE_SIZE
+        * PAGE_SIZE;
+    Ok((addr, end))
+}
+
+// 页表项无法表示只写或不可读的页面，这里总是允许读
+fn prot_to_attr(prot: usize) -> MemoryAttr {
+    let mut attr = MemoryAttr::new().set_user();
+    if prot & PROT_WRITE == 0 {
+        attr = attr.set_readonly();
+    }
+    if prot & PROT_EXEC != 0 {
+        attr = attr.set_execute();
+    }
+    attr
+}
@@ -0,0 +1,88 @@
+mod errno;
+mod fs;
+mod mm;
+mod process;
+mod uaccess;
+
+use crate::context::TrapFrame;
+pub use errno::Errno;
+use fs::*;
+use mm::*;
+use process::*;
+
+pub const SYS_GETCWD: usize = 17;
+pub const SYS_MKDIR: usize = 34;
+pub const SYS_UNLINK: usize = 35;
+pub const SYS_RENAME: usize = 38;
+pub const SYS_CHDIR: usize = 49;
+pub const SYS_OPEN: usize = 56;
+pub const SYS_CLOSE: usize = 57;
+pub const SYS_PIPE: usize = 59;
+pub const SYS_GETDENTS: usize = 61;
+pub const SYS_LSEEK: usize = 62;
+pub const SYS_WRITE: usize = 64;
+pub const SYS_PREAD: usize = 67;
+pub const SYS_PWRITE: usize = 68;
+pub const SYS_STAT: usize = 79;
+pub const SYS_FSTAT: usize = 80;
+pub const SYS_EXIT: usize = 93;
+pub const SYS_READ: usize = 63;
+pub const SYS_YIELD: usize = 124;
+pub const SYS_SET_PRIORITY: usize = 140;
+pub const SYS_GETTIME: usize = 169;
+pub const SYS_GETPID: usize = 172;
+pub const SYS_GETPPID: usize = 173;
+pub const SYS_BRK: usize = 214;
+pub const SYS_MUNMAP: usize = 215;
+pub const SYS_FORK: usize = 220;
+pub const SYS_EXEC: usize = 221;
+pub const SYS_MMAP: usize = 222;
+pub const SYS_MPROTECT: usize = 226;
+pub const SYS_MSYNC: usize = 227;
+pub const SYS_WAIT: usize = 260;
+
+// 成功时为返回值，失败时为错误码
+pub type SysResult = Result<usize, Errno>;
+
+pub fn syscall(id: usize, args: [usize; 6], tf: &mut TrapFrame) -> isize {
+    let ret = match id {
+        SYS_GETCWD => sys_getcwd(args[0], args[1]),
+        SYS_MKDIR => sys_mkdir(args[0]),
+        SYS_UNLINK => sys_unlink(args[0], args[1]),
+        SYS_RENAME => sys_rename(args[0], args[1]),
+        SYS_CHDIR => sys_chdir(args[0]),
+        SYS_OPEN => sys_open(args[0], args[1] as i32),
+        SYS_CLOSE => sys_close(args[0]),
+        SYS_PIPE => sys_pipe(args[0]),
+        SYS_GETDENTS => sys_getdents(args[0], args[1], args[2]),
+        SYS_READ => sys_read(args[0], args[1], args[2]),
+        SYS_WRITE => sys_write(args[0], args[1], args[2]),
+        SYS_LSEEK => sys_lseek(args[0], args[1], args[2]),
+        SYS_PREAD => sys_pread(args[0], args[1], args[2], args[3]),
+        SYS_PWRITE => sys_pwrite(args[0], args[1], args[2], args[3]),
+        SYS_STAT => sys_stat(args[0], args[1]),
+        SYS_FSTAT => sys_fstat(args[0], args[1]),
+        SYS_EXIT => sys_exit(args[0]),
+        SYS_FORK => sys_fork(tf),
+        SYS_EXEC => sys_exec(args[0], args[1], tf),
+        SYS_YIELD => sys_yield(),
+        SYS_SET_PRIORITY => sys_set_priority(args[0]),
+        SYS_GETTIME => sys_gettime(),
+        SYS_GETPID => sys_getpid(),
+        SYS_GETPPID => sys_getppid(),
+        SYS_WAIT => sys_wait(args[0], args[1]),
+        SYS_BRK => sys_brk(args[0]),
+        SYS_MMAP => sys_mmap(args[0], args[1], args[2], args[3], args[4], args[5]),
+        SYS_MUNMAP => sys_munmap(args[0], args[1]),
+        SYS_MPROTECT => sys_mprotect(args[0], args[1], args[2]),
+        SYS_MSYNC => sys_msync(args[0], args[1], args[2]),
+        _ => {
+            println!("unknown syscall id {}", id);
+            Err(Errno::ENOSYS)
+        }
+    };
+    match ret {
+        Ok(value) => value as isize,
+        Err(errno) => -(errno as isize),
+    }
+}
@@ -0,0 +1,94 @@
+use super::fs::current_cwd;
+use super::uaccess::*;
+use super::{Errno, SysResult};
+use crate::context::TrapFrame;
+use crate::fs::{path, INodeExt};
+use crate::memory::memory_set::attr::AccessType;
+use crate::process;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// 所有参数连同 argv 数组所占空间的上限
+const MAX_ARG_LEN: usize = 0x8000;
+// 参数个数的上限
+const MAX_ARGS: usize = 256;
+
+pub fn sys_exit(code: usize) -> SysResult {
+    process::exit(code);
+    Ok(0)
+}
+
+pub fn sys_fork(tf: &mut TrapFrame) -> SysResult {
+    process::fork(tf)
+}
+
+// argv 是以空指针结尾的字符串指针数组，可以为空
+// 成功时不返回到原程序，新程序的 a0 即为 argc
+pub fn sys_exec(path: usize, argv: usize, tf: &mut TrapFrame) -> SysResult {
+    // 原地址空间即将被替换，先把路径与参数复制到内核中
+    let path = copy_cstr_from_user(path)?;
+    let ptr_size = core::mem::size_of::<usize>();
+    let mut args: Vec<String> = Vec::new();
+    // 边复制边累计所占空间，超出上限时立即停止，以免先耗尽内核堆
+    let mut total = 0;
+    if argv != 0 {
+        loop {
+            let ptr = argv.checked_add(args.len() * ptr_size);
+            let arg: usize = read_user(ptr.ok_or(Errno::EFAULT)?)?;
+            if arg == 0 {
+                break;
+            }
+            if args.len() >= MAX_ARGS {
+                return Err(Errno::E2BIG);
+            }
+            let arg = copy_cstr_from_user(arg)?;
+            total += arg.len() + 1 + ptr_size;
+            if total > MAX_ARG_LEN {
+                return Err(Errno::E2BIG);
+            }
+            args.push(arg);
+        }
+    }
+    let data = path::lookup(&current_cwd(), &path)?.read_as_vec()?;
+    Ok(process::exec(data.as_slice(), args.as_slice(), tf)?)
+}
+
+pub fn sys_set_priority(priority: usize) -> SysResult {
+    if priority == 0 {
+        return Err(Errno::EINVAL);
+    }
+    process::set_priority(priority);
+    Ok(0)
+}
+
+// 返回以毫秒为单位的时间
+pub fn sys_gettime() -> SysResult {
+    Ok(crate::timer::get_time_ms())
+}
+
+pub fn sys_getpid() -> SysResult {
+    Ok(process::current_tid())
+}
+
+// 没有父进程时返回 0
+pub fn sys_getppid() -> SysResult {
+    Ok(process::current_ppid().unwrap_or(0))
+}
+
+pub fn sys_yield() -> SysResult {
+    process::sched_yield();
+    Ok(0)
+}
+
+// 成功回收子线程时返回其 tid 并写入其退出码，没有符合条件的子线程时返回 ECHILD
+pub fn sys_wait(pid: usize, code: usize) -> SysResult {
+    // 回收子线程之后就无法再报告错误了，因此先检查 code 是否可写
+    if code != 0 {
+        check_user_range(code, core::mem::size_of::<i32>(), AccessType::Write)?;
+    }
+    let (tid, exit_code) = process::wait(pid).ok_or(Errno::ECHILD)?;
+    if code != 0 {
+        write_user(code, &(exit_code as i32))?;
+    }
+    Ok(tid)
+}
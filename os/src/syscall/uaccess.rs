@@ -0,0 +1,81 @@
+// 系统调用访问用户内存的唯一途径：先根据当前线程的地址空间检查访问是否合法，
+// 再通过页表翻译成物理地址进行复制，因此内核不会因为错误的用户指针而缺页
+use super::Errno;
+use crate::consts::PAGE_SIZE;
+use crate::memory::memory_set::attr::AccessType;
+use crate::process;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// 用户传入的路径等字符串的最大长度
+const MAX_CSTR_LEN: usize = 4096;
+
+// 只检查而不访问，用于在阻塞或产生副作用之前提前发现错误的指针
+pub fn check_user_range(va: usize, len: usize, access: AccessType) -> Result<(), Errno> {
+    if len == 0 {
+        return Ok(());
+    }
+    let end = va.checked_add(len).ok_or(Errno::EFAULT)?;
+    let vm = process::current_vm().ok_or(Errno::EFAULT)?;
+    if vm.lock().check_user_range(va, end, access) {
+        Ok(())
+    } else {
+        Err(Errno::EFAULT)
+    }
+}
+
+pub fn copy_from_user(dst: &mut [u8], src: usize) -> Result<(), Errno> {
+    let vm = process::current_vm().ok_or(Errno::EFAULT)?;
+    if dst.is_empty() || vm.lock().copy_from_user(dst, src) {
+        Ok(())
+    } else {
+        Err(Errno::EFAULT)
+    }
+}
+
+pub fn copy_to_user(dst: usize, src: &[u8]) -> Result<(), Errno> {
+    let vm = process::current_vm().ok_or(Errno::EFAULT)?;
+    if src.is_empty() || vm.lock().copy_to_user(dst, src) {
+        Ok(())
+    } else {
+        Err(Errno::EFAULT)
+    }
+}
+
+pub fn read_user<T: Copy + Default>(src: usize) -> Result<T, Errno> {
+    let mut value = T::default();
+    let dst = unsafe {
+        core::slice::from_raw_parts_mut(&mut value as *mut T as *mut u8, core::mem::size_of::<T>())
+    };
+    copy_from_user(dst, src)?;
+    Ok(value)
+}
+
+pub fn write_user<T: Copy>(dst: usize, value: &T) -> Result<(), Errno> {
+    let src = unsafe {
+        core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>())
+    };
+    copy_to_user(dst, src)
+}
+
+// 复制以 '\0' 结尾的字符串
+pub fn copy_cstr_from_user(src: usize) -> Result<String, Errno> {
+    let mut bytes = Vec::new();
+    let mut va = src;
+    loop {
+        // 每次最多读到页尾，避免越过字符串所在的区域
+        let size = PAGE_SIZE - va % PAGE_SIZE;
+        let mut buf = [0u8; PAGE_SIZE];
+        copy_from_user(&mut buf[..size], va)?;
+        if let Some(len) = buf[..size].iter().position(|&c| c == 0) {
+            bytes.extend_from_slice(&buf[..len]);
+            break;
+        }
+        bytes.extend_from_slice(&buf[..size]);
+        if bytes.len() >= MAX_CSTR_LEN {
+            return Err(Errno::ENAMETOOLONG);
+        }
+        va += size;
+    }
+    String::from_utf8(bytes).map_err(|_| Errno::EINVAL)
+}
@@ -4,6 +4,8 @@ use riscv::register::{sie, time};
 pub static mut TICKS: usize = 0;
 
 static TIMEBASE: u64 = 100000;
+// qemu 中 time 寄存器的计数频率为 10MHz
+static CLOCK_FREQ: u64 = 10_000_000;
 pub fn init() {
     unsafe {
         TICKS = 0;
@@ -20,3 +22,8 @@ pub fn clock_set_next_event() {
 fn get_cycle() -> u64 {
     time::read() as u64
 }
+
+// 开机以来经过的毫秒数
+pub fn get_time_ms() -> usize {
+    (get_cycle() / (CLOCK_FREQ / 1000)) as usize
+}
@@ -11,6 +11,21 @@
 	csrrw sp, sscratch, sp
 	bnez sp, trap_from_user
 trap_from_kernel:
+	# 此时 sscratch 中为原来的 sp，sp 可以暂作计算使用
+	# 若保存现场会写入内核栈区域某个槽位的低半部分（保护页），说明内核栈已经溢出，改用备用栈
+	csrr sp, sscratch
+	addi sp, sp, -36*XLENB
+	srai sp, sp, 30
+	addi sp, sp, 2
+	bnez sp, 1f
+	csrr sp, sscratch
+	addi sp, sp, -36*XLENB
+	srli sp, sp, 19
+	andi sp, sp, 1
+	bnez sp, 1f
+	la sp, kernel_overflow_stack_top
+	j trap_from_user
+1:
 	csrr sp, sscratch
 trap_from_user:
 	addi sp, sp, -36*XLENB
@@ -114,3 +129,12 @@ __alltraps:
 __trapret:
 	RESTORE_ALL
 	sret
+
+	# 内核栈溢出时用于保存现场并报告错误的备用栈
+	.section .bss.overflow_stack
+	.align 12
+	.globl kernel_overflow_stack
+kernel_overflow_stack:
+	.space 4096 * 4
+	.globl kernel_overflow_stack_top
+kernel_overflow_stack_top:
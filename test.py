@@ -6,6 +6,7 @@ tests = {
     'lab3': (False, 'vm_test.rs'),
     'labuser': (True, 'test_test.rs'),
     'lab5': (True, 'fork_test.rs'),
+    'lab5-wait': (True, 'wait_test.rs'),
     'lab6': (True, 'stride_test.rs'),
     'lab7': (False, 'mutex_test.rs'),
     'lab8': (True, 'pipe_test.rs'),
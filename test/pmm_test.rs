@@ -6,6 +6,7 @@ use crate::memory::{alloc_frame, dealloc_frame};
 #[no_mangle]
 pub extern "C" fn rust_main() -> ! {
     let FF_grade = FirstFitAllocator_test();
+    let BD_grade = BuddyAllocator_test();
     extern "C" {
         fn end();
     }
@@ -14,9 +15,11 @@ pub extern "C" fn rust_main() -> ! {
         PHYSICAL_MEMORY_END >> 12,
     );
     println!("First Fit Allocator: {} / 8", FF_grade);
+    println!("Buddy Allocator: {} / 8", BD_grade);
     crate::sbi::shutdown();
 }
 
+use crate::memory::frame_allocator::{FrameAllocator, BUDDY_ALLOCATOR};
 use riscv::addr::Frame;
 
 fn alloc(cnt: usize) -> Option<usize> {
@@ -96,3 +99,61 @@ fn FirstFitAllocator_test() -> usize {
     dealloc(p0.unwrap(), 5);
     return grade;
 }
+
+fn BuddyAllocator_test() -> usize {
+    let mut grade: usize = 0;
+    let mut allocator = BUDDY_ALLOCATOR.lock();
+    allocator.init(1, 9);
+    // 不足 2 的幂的请求向上取整
+    let p0 = allocator.alloc(5);
+    if p0 != Some(1) || allocator.alloc(1).is_some() {
+        return grade;
+    } else {
+        grade += 1;
+    }
+    allocator.dealloc(1, 5);
+    if allocator.alloc(1) != Some(1) || allocator.alloc(2) != Some(3) {
+        return grade;
+    } else {
+        grade += 1;
+    }
+    // 较小的块从已被拆开的伙伴中分配
+    if allocator.alloc(1) != Some(2) {
+        return grade;
+    } else {
+        grade += 1;
+    }
+    if allocator.alloc(3) != Some(5) {
+        return grade;
+    } else {
+        grade += 1;
+    }
+    if allocator.alloc(1).is_some() {
+        return grade;
+    } else {
+        grade += 1;
+    }
+    // 相邻的伙伴都被释放后合并
+    allocator.dealloc(2, 1);
+    allocator.dealloc(1, 1);
+    if allocator.alloc(2) != Some(1) {
+        return grade;
+    } else {
+        grade += 1;
+    }
+    allocator.dealloc(1, 2);
+    allocator.dealloc(3, 2);
+    allocator.dealloc(5, 3);
+    if allocator.alloc(8) != Some(1) {
+        return grade;
+    } else {
+        grade += 1;
+    }
+    if allocator.alloc(1).is_some() {
+        return grade;
+    } else {
+        grade += 1;
+    }
+    allocator.dealloc(1, 8);
+    return grade;
+}
@@ -33,7 +33,7 @@ pub fn main() -> isize {
     println!("I am the parent, waiting now..");
     let wait_pid = waitpid(pid, &mut code);
     println!("{}, {:x}", wait_pid, code);
-    if wait_pid != 0 || code != magic as i32 {
+    if wait_pid != pid as i64 || code != magic as i32 {
         panic!("wait_test1 fail");
     }
     if !(waitpid(pid, &mut code) != 0) {
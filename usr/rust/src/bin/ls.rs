@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user;
+
+use user::env;
+use user::sys::{read_dir, DT_DIR, DT_LNK};
+
+// 列出各参数所指目录中的文件，没有参数时列出当前目录
+#[no_mangle]
+pub fn main() -> usize {
+    let mut paths = env::args().skip(1).peekable();
+    if paths.peek().is_none() {
+        return list(".");
+    }
+    let mut code = 0;
+    for path in paths {
+        code |= list(path);
+    }
+    code
+}
+
+fn list(path: &str) -> usize {
+    match read_dir(path) {
+        Ok(entries) => {
+            for entry in entries.iter() {
+                match entry.type_ {
+                    DT_DIR => println!("{}/", entry.name),
+                    DT_LNK => println!("{}@", entry.name),
+                    _ => println!("{}", entry.name),
+                }
+            }
+            0
+        }
+        Err(errno) => {
+            println!("ls: {}: {:?}", path, errno);
+            1
+        }
+    }
+}
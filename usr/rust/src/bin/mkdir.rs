@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user;
+
+use user::env;
+use user::sys::mkdir;
+
+// 依次创建各参数所指的目录
+#[no_mangle]
+pub fn main() -> usize {
+    let mut code = 0;
+    for arg in env::args().skip(1) {
+        if let Err(errno) = mkdir(arg) {
+            println!("mkdir: {}: {:?}", arg, errno);
+            code = 1;
+        }
+    }
+    code
+}
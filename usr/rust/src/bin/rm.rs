@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user;
+
+use user::env;
+use user::errno::Errno;
+use user::sys::{rmdir, unlink};
+
+// 删除各参数所指的文件，带有 -d 时也可以删除空目录
+#[no_mangle]
+pub fn main() -> usize {
+    let mut dir = false;
+    let mut code = 0;
+    for arg in env::args().skip(1) {
+        if arg == "-d" {
+            dir = true;
+            continue;
+        }
+        let result = match unlink(arg) {
+            Err(Errno::EISDIR) if dir => rmdir(arg),
+            result => result,
+        };
+        if let Err(errno) = result {
+            println!("rm: {}: {:?}", arg, errno);
+            code = 1;
+        }
+    }
+    code
+}
@@ -10,8 +10,49 @@ const LF: u8 = 0x0au8;
 const CR: u8 = 0x0du8;
 
 use alloc::string::String;
+use alloc::vec::Vec;
+use user::errno::Errno;
 use user::io::getc;
-use user::syscall::sys_exec;
+use user::sys::{chdir, exec, exit, fork, getcwd, wait};
+
+// 以空白分隔命令行，在子进程中执行第一个参数所指的程序并等待其退出
+fn run(line: &str) {
+    let args: Vec<&str> = line.split_whitespace().collect();
+    if args.is_empty() {
+        return;
+    }
+    // 工作目录属于 shell 自身，需要在 shell 中处理
+    match args[0] {
+        "cd" => {
+            if let Err(errno) = chdir(args.get(1).copied().unwrap_or("/")) {
+                println!("cd failed: {:?}", errno);
+            }
+            return;
+        }
+        "pwd" => {
+            match getcwd() {
+                Ok(cwd) => println!("{}", cwd),
+                Err(errno) => println!("pwd failed: {:?}", errno),
+            }
+            return;
+        }
+        _ => {}
+    }
+    println!("searching for program {}", line);
+    match fork() {
+        Ok(0) => {
+            match exec(args[0], &args) {
+                Errno::ENOENT => println!("command not found!"),
+                errno => println!("exec failed: {:?}", errno),
+            }
+            exit(1);
+        }
+        Ok(pid) => {
+            let _ = wait(pid);
+        }
+        Err(errno) => println!("fork failed: {:?}", errno),
+    }
+}
 
 #[no_mangle]
 pub fn main() {
@@ -24,9 +65,7 @@ pub fn main() {
             LF | CR => {
                 println!();
                 if !line.is_empty() {
-                    line.push('\0');
-                    println!("searching for program {}", line);
-                    sys_exec(line.as_ptr());
+                    run(line.as_str());
                     line.clear();
                 }
                 print!(">> ");
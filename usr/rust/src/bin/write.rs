@@ -10,22 +10,22 @@ use user::io::*;
 use user::syscall::{sys_close, sys_open, sys_read, sys_write};
 
 const BUFFER_SIZE: usize = 20;
-const FILE: &str = "temp\0";
+const FILE: &str = "hello\0";
 const TEXT: &str = "Hello world!\0";
 
 #[no_mangle]
 pub fn main() -> usize {
-    // 将字符串写到文件 temp 中
-    let write_fd = sys_open(FILE.as_ptr(), O_WRONLY);
+    // 创建文件 hello 并将字符串写入其中
+    let write_fd = sys_open(FILE.as_ptr(), O_WRONLY | O_CREAT | O_TRUNC);
     sys_write(write_fd as usize, TEXT.as_ptr(), TEXT.len());
-    println!("write to file 'temp' successfully...");
+    println!("write to file 'hello' successfully...");
     sys_close(write_fd as i32);
 
-    // 将字符串从文件 temp 读入内存
+    // 将字符串从文件 hello 读入内存
     let read_fd = sys_open(FILE.as_ptr(), O_RDONLY);
-    let read = [0u8; BUFFER_SIZE];
-    sys_read(read_fd as usize, read[0] as *mut u8, BUFFER_SIZE);
-    println!("read from file 'temp' successfully...");
+    let mut read = [0u8; BUFFER_SIZE];
+    sys_read(read_fd as usize, read.as_mut_ptr(), BUFFER_SIZE);
+    println!("read from file 'hello' successfully...");
 
     // 检查功能是否正确
     let len = (0..BUFFER_SIZE).find(|&i| read[i] as u8 == 0).unwrap();
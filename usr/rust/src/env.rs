@@ -0,0 +1,37 @@
+// 由 _start 记录的命令行参数
+static mut ARGC: usize = 0;
+static mut ARGV: *const *const u8 = core::ptr::null();
+
+pub(crate) fn init(argc: usize, argv: *const *const u8) {
+    unsafe {
+        ARGC = argc;
+        ARGV = argv;
+    }
+}
+
+pub struct Args {
+    index: usize,
+}
+
+// 遍历命令行参数，第一个参数为程序路径
+pub fn args() -> Args {
+    Args { index: 0 }
+}
+
+impl Iterator for Args {
+    type Item = &'static str;
+
+    fn next(&mut self) -> Option<&'static str> {
+        unsafe {
+            if self.index >= ARGC {
+                return None;
+            }
+            let arg = *ARGV.add(self.index);
+            self.index += 1;
+            let len = (0usize..).find(|&i| *arg.add(i) == 0).unwrap();
+            Some(core::str::from_utf8_unchecked(core::slice::from_raw_parts(
+                arg, len,
+            )))
+        }
+    }
+}
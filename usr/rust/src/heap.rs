@@ -0,0 +1,38 @@
+use crate::sys::sbrk;
+use buddy_system_allocator::LockedHeap;
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::NonNull;
+
+// 每次至少向内核申请这么多堆空间
+const HEAP_INCREMENT: usize = 0x4000;
+
+// 初始时为空，空间不足时通过 sbrk 向内核申请更多内存
+pub struct GrowableHeap(LockedHeap);
+
+impl GrowableHeap {
+    pub const fn empty() -> Self {
+        GrowableHeap(LockedHeap::empty())
+    }
+}
+
+unsafe impl GlobalAlloc for GrowableHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        loop {
+            if let Ok(ptr) = heap.alloc(layout) {
+                return ptr.as_ptr();
+            }
+            // 伙伴系统按 2 的幂对齐分块，扩展两倍大小才能保证放得下
+            let block = layout.size().max(layout.align()).next_power_of_two();
+            let size = (block * 2).max(HEAP_INCREMENT);
+            match sbrk(size as isize) {
+                Ok(start) => heap.add_to_heap(start, start + size),
+                Err(_) => return core::ptr::null_mut(),
+            }
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().dealloc(NonNull::new_unchecked(ptr), layout)
+    }
+}
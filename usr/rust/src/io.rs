@@ -43,7 +43,7 @@ pub const STDOUT: usize = 1;
 
 pub fn getc() -> u8 {
     let mut c = 0u8;
-    assert_eq!(sys_read(STDIN, &mut c, 1), 1);
+    assert_eq!(sys_read(STDIN, &mut c as *mut u8, 1), 1);
     c
 }
 
@@ -51,4 +51,5 @@ pub const O_RDONLY: i32 = 0; // 只读
 pub const O_WRONLY: i32 = 1; // 只写
 pub const O_RDWR: i32 = 2; // 可读可写
 pub const O_CREAT: i32 = 64; // 打开文件时若文件不存在，创建它
+pub const O_TRUNC: i32 = 512; // 以可写方式打开时清空文件
 pub const O_APPEND: i32 = 1024; // 从文件结尾开始写入
@@ -8,18 +8,6 @@ fn main() -> usize {
     panic!("No main() linked");
 }
 
-use crate::DYNAMIC_ALLOCATOR;
-
-fn init_heap() {
-    const HEAP_SIZE: usize = 0x1000;
-    static mut HEAP: [u8; HEAP_SIZE] = [0; HEAP_SIZE];
-    unsafe {
-        DYNAMIC_ALLOCATOR
-            .lock()
-            .init(HEAP.as_ptr() as usize, HEAP_SIZE);
-    }
-}
-
 #[panic_handler]
 fn panic(_info: &PanicInfo) -> ! {
     let location = _info.location().unwrap();
@@ -34,8 +22,8 @@ fn panic(_info: &PanicInfo) -> ! {
 }
 
 #[no_mangle]
-pub extern "C" fn _start(_args: isize, _argv: *const u8) -> ! {
-    init_heap();
+pub extern "C" fn _start(argc: usize, argv: *const *const u8) -> ! {
+    crate::env::init(argc, argv);
     sys_exit(main())
 }
 
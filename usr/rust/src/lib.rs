@@ -9,10 +9,14 @@ extern crate alloc;
 #[macro_use]
 pub mod io;
 
+pub mod env;
+pub mod errno;
+mod heap;
 pub mod lang_items;
+pub mod sys;
 pub mod syscall;
 
-use buddy_system_allocator::LockedHeap;
+use heap::GrowableHeap;
 
 #[global_allocator]
-static DYNAMIC_ALLOCATOR: LockedHeap = LockedHeap::empty();
+static DYNAMIC_ALLOCATOR: GrowableHeap = GrowableHeap::empty();
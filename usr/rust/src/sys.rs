@@ -0,0 +1,314 @@
+// 对 syscall 中系统调用的封装，以 Result 的形式返回错误
+use crate::errno::{Errno, Result};
+use crate::syscall::*;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+fn check(ret: i64) -> Result<usize> {
+    if ret < 0 {
+        Err(Errno::from_code(-ret))
+    } else {
+        Ok(ret as usize)
+    }
+}
+
+// 转换为以 '\0' 结尾的字符串
+fn to_cstr(s: &str) -> String {
+    let mut s = String::from(s);
+    s.push('\0');
+    s
+}
+
+pub fn open(path: &str, flags: i32) -> Result<usize> {
+    let path = to_cstr(path);
+    check(sys_open(path.as_ptr(), flags))
+}
+
+pub fn close(fd: usize) -> Result<()> {
+    check(sys_close(fd as i32)).map(|_| ())
+}
+
+pub fn read(fd: usize, buf: &mut [u8]) -> Result<usize> {
+    check(sys_read(fd, buf.as_mut_ptr(), buf.len()))
+}
+
+pub fn write(fd: usize, buf: &[u8]) -> Result<usize> {
+    check(sys_write(fd, buf.as_ptr(), buf.len()))
+}
+
+pub const SEEK_SET: usize = 0; // 相对文件开头
+pub const SEEK_CUR: usize = 1; // 相对当前偏移
+pub const SEEK_END: usize = 2; // 相对文件末尾
+
+// 移动 fd 的读写偏移，返回新的偏移
+pub fn lseek(fd: usize, offset: isize, whence: usize) -> Result<usize> {
+    check(sys_lseek(fd, offset, whence))
+}
+
+// 从 offset 处读取，不改变 fd 的偏移
+pub fn pread(fd: usize, buf: &mut [u8], offset: usize) -> Result<usize> {
+    check(sys_pread(fd, buf.as_mut_ptr(), buf.len(), offset))
+}
+
+// 写到 offset 处，不改变 fd 的偏移
+pub fn pwrite(fd: usize, buf: &[u8], offset: usize) -> Result<usize> {
+    check(sys_pwrite(fd, buf.as_ptr(), buf.len(), offset))
+}
+
+// 返回管道的读端与写端
+pub fn pipe() -> Result<(usize, usize)> {
+    let mut fds = [0i32; 2];
+    check(sys_pipe(&mut fds))?;
+    Ok((fds[0] as usize, fds[1] as usize))
+}
+
+pub fn exit(code: usize) -> ! {
+    sys_exit(code)
+}
+
+pub fn fork() -> Result<usize> {
+    check(sys_fork())
+}
+
+pub fn chdir(path: &str) -> Result<()> {
+    let path = to_cstr(path);
+    check(sys_chdir(path.as_ptr())).map(|_| ())
+}
+
+// 返回当前工作目录的绝对路径
+pub fn getcwd() -> Result<String> {
+    let mut buf = [0u8; 256];
+    let len = check(sys_getcwd(buf.as_mut_ptr(), buf.len()))?;
+    let path = core::str::from_utf8(&buf[..len - 1]).map_err(|_| Errno::EINVAL)?;
+    Ok(String::from(path))
+}
+
+// 只有失败时才会返回
+pub fn exec(path: &str, args: &[&str]) -> Errno {
+    let path = to_cstr(path);
+    let args: Vec<String> = args.iter().map(|&arg| to_cstr(arg)).collect();
+    let mut argv: Vec<*const u8> = args.iter().map(|arg| arg.as_ptr()).collect();
+    argv.push(core::ptr::null());
+    match check(sys_exec(path.as_ptr(), argv.as_ptr())) {
+        Ok(_) => unreachable!(),
+        Err(errno) => errno,
+    }
+}
+
+// pid 为 0 时等待任意子进程，返回被回收的子进程的 pid 及其退出码
+pub fn wait(pid: usize) -> Result<(usize, i32)> {
+    let mut code: i32 = 0;
+    let pid = check(sys_wait(pid, &mut code))?;
+    Ok((pid, code))
+}
+
+pub fn yield_now() {
+    sys_yield();
+}
+
+pub fn set_priority(priority: usize) -> Result<()> {
+    check(crate::syscall::set_priority(priority)).map(|_| ())
+}
+
+pub fn gettime() -> usize {
+    sys_gettime()
+}
+
+pub fn getpid() -> usize {
+    sys_getpid()
+}
+
+pub fn getppid() -> usize {
+    sys_getppid()
+}
+
+// 将堆顶设为 addr，返回新的堆顶
+pub fn brk(addr: usize) -> Result<usize> {
+    let brk = check(sys_brk(addr))?;
+    if brk != addr {
+        return Err(Errno::ENOMEM);
+    }
+    Ok(brk)
+}
+
+// 将堆顶移动 increment 字节，返回原来的堆顶
+pub fn sbrk(increment: isize) -> Result<usize> {
+    let old = check(sys_brk(0))?;
+    if increment != 0 {
+        brk((old as isize + increment) as usize)?;
+    }
+    Ok(old)
+}
+
+pub const PROT_READ: usize = 1; // 可读
+pub const PROT_WRITE: usize = 2; // 可写
+pub const PROT_EXEC: usize = 4; // 可执行
+
+pub const MAP_SHARED: usize = 0x1; // 修改对其他映射可见
+pub const MAP_PRIVATE: usize = 0x2; // 写时复制的私有映射
+pub const MAP_FIXED: usize = 0x10; // 必须映射到 addr 处
+pub const MAP_ANONYMOUS: usize = 0x20; // 不对应任何文件，内容初始为 0
+
+pub const MS_ASYNC: usize = 1; // 异步写回
+pub const MS_INVALIDATE: usize = 2; // 使其他映射失效
+pub const MS_SYNC: usize = 4; // 写回完成后才返回
+
+// 将文件 fd 从 offset 开始的内容映射到内存，返回映射的起始地址
+pub fn mmap(
+    addr: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: usize,
+    offset: usize,
+) -> Result<usize> {
+    check(sys_mmap(addr, len, prot, flags, fd, offset))
+}
+
+// 建立一段匿名映射，返回映射的起始地址
+pub fn mmap_anonymous(addr: usize, len: usize, prot: usize, flags: usize) -> Result<usize> {
+    check(sys_mmap(addr, len, prot, flags | MAP_ANONYMOUS, 0, 0))
+}
+
+pub fn munmap(addr: usize, len: usize) -> Result<()> {
+    check(sys_munmap(addr, len)).map(|_| ())
+}
+
+pub fn mprotect(addr: usize, len: usize, prot: usize) -> Result<()> {
+    check(sys_mprotect(addr, len, prot)).map(|_| ())
+}
+
+// 将共享文件映射中被修改的内容写回文件
+pub fn msync(addr: usize, len: usize, flags: usize) -> Result<()> {
+    check(sys_msync(addr, len, flags)).map(|_| ())
+}
+
+pub const AT_REMOVEDIR: usize = 0x200; // 删除的是目录
+
+pub fn mkdir(path: &str) -> Result<()> {
+    let path = to_cstr(path);
+    check(sys_mkdir(path.as_ptr())).map(|_| ())
+}
+
+// 删除目录以外的文件
+pub fn unlink(path: &str) -> Result<()> {
+    let path = to_cstr(path);
+    check(sys_unlink(path.as_ptr(), 0)).map(|_| ())
+}
+
+// 删除空目录
+pub fn rmdir(path: &str) -> Result<()> {
+    let path = to_cstr(path);
+    check(sys_unlink(path.as_ptr(), AT_REMOVEDIR)).map(|_| ())
+}
+
+pub fn rename(old_path: &str, new_path: &str) -> Result<()> {
+    let old_path = to_cstr(old_path);
+    let new_path = to_cstr(new_path);
+    check(sys_rename(old_path.as_ptr(), new_path.as_ptr())).map(|_| ())
+}
+
+pub const DT_DIR: u8 = 4; // 目录
+pub const DT_REG: u8 = 8; // 普通文件
+pub const DT_LNK: u8 = 10; // 符号链接
+
+pub struct DirEntry {
+    pub ino: u64,
+    pub type_: u8,
+    pub name: String,
+}
+
+// 读出目录 path 中的所有目录项，包括 . 与 ..
+pub fn read_dir(path: &str) -> Result<Vec<DirEntry>> {
+    let fd = open(path, crate::io::O_RDONLY)?;
+    let result = read_dir_fd(fd);
+    close(fd)?;
+    result
+}
+
+fn read_dir_fd(fd: usize) -> Result<Vec<DirEntry>> {
+    let mut entries = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        let len = check(sys_getdents(fd, buf.as_mut_ptr(), buf.len()))?;
+        if len == 0 {
+            return Ok(entries);
+        }
+        let mut pos = 0;
+        while pos < len {
+            let field = |offset: usize, size: usize| {
+                let mut bytes = [0u8; 8];
+                bytes[..size].copy_from_slice(&buf[pos + offset..pos + offset + size]);
+                u64::from_le_bytes(bytes)
+            };
+            let ino = field(0, 8);
+            let reclen = field(16, 2) as usize;
+            let type_ = buf[pos + 18];
+            let name = &buf[pos + 19..pos + reclen];
+            let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            let name = core::str::from_utf8(&name[..name_len]).map_err(|_| Errno::EINVAL)?;
+            entries.push(DirEntry {
+                ino,
+                type_,
+                name: String::from(name),
+            });
+            pos += reclen;
+        }
+    }
+}
+
+pub const S_IFMT: u32 = 0o170000; // st_mode 中表示文件类型的部分
+pub const S_IFIFO: u32 = 0o010000; // 管道
+pub const S_IFCHR: u32 = 0o020000; // 字符设备
+pub const S_IFDIR: u32 = 0o040000; // 目录
+pub const S_IFBLK: u32 = 0o060000; // 块设备
+pub const S_IFREG: u32 = 0o100000; // 普通文件
+pub const S_IFLNK: u32 = 0o120000; // 符号链接
+pub const S_IFSOCK: u32 = 0o140000; // 套接字
+
+// 与内核写入的 struct stat 布局一致
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    _pad1: u64,
+    pub size: i64,
+    pub blksize: i32,
+    _pad2: i32,
+    pub blocks: i64,
+    pub atime: i64,
+    pub atime_nsec: u64,
+    pub mtime: i64,
+    pub mtime_nsec: u64,
+    pub ctime: i64,
+    pub ctime_nsec: u64,
+    _unused: [u32; 2],
+}
+
+impl Stat {
+    pub fn is_dir(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+    pub fn is_file(&self) -> bool {
+        self.mode & S_IFMT == S_IFREG
+    }
+}
+
+pub fn stat(path: &str) -> Result<Stat> {
+    let path = to_cstr(path);
+    let mut stat = Stat::default();
+    check(sys_stat(path.as_ptr(), &mut stat))?;
+    Ok(stat)
+}
+
+pub fn fstat(fd: usize) -> Result<Stat> {
+    let mut stat = Stat::default();
+    check(sys_fstat(fd, &mut stat))?;
+    Ok(stat)
+}
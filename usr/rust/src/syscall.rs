@@ -1,21 +1,56 @@
+use crate::sys::Stat;
+
 enum SyscallId {
+    GetCwd = 17,
+    Mkdir = 34,
+    Unlink = 35,
+    Rename = 38,
+    Chdir = 49,
     Open = 56,
     Close = 57,
+    Pipe = 59,
+    GetDents = 61,
+    Lseek = 62,
     Read = 63,
     Write = 64,
+    Pread = 67,
+    Pwrite = 68,
+    Stat = 79,
+    Fstat = 80,
     Exit = 93,
+    Yield = 124,
+    SetPriority = 140,
+    GetTime = 169,
+    GetPid = 172,
+    GetPPid = 173,
+    Brk = 214,
+    Munmap = 215,
+    Fork = 220,
     Exec = 221,
+    Mmap = 222,
+    Mprotect = 226,
+    Msync = 227,
+    Wait = 260,
 }
 
 #[inline(always)]
-fn sys_call(syscall_id: SyscallId, arg0: usize, arg1: usize, arg2: usize, arg3: usize) -> i64 {
+fn sys_call(
+    syscall_id: SyscallId,
+    arg0: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> i64 {
     let id = syscall_id as usize;
     let mut ret: i64;
     unsafe {
         llvm_asm!(
             "ecall"
             : "={x10}"(ret)
-            : "{x17}"(id), "{x10}"(arg0), "{x11}"(arg1), "{x12}"(arg2), "{x13}"(arg3)
+            : "{x17}"(id), "{x10}"(arg0), "{x11}"(arg1), "{x12}"(arg2), "{x13}"(arg3),
+              "{x14}"(arg4), "{x15}"(arg5)
             : "memory"
             : "volatile"
         );
@@ -23,27 +58,147 @@ fn sys_call(syscall_id: SyscallId, arg0: usize, arg1: usize, arg2: usize, arg3:
     ret
 }
 
+// 成功时返回写入 buf 的长度，包括结尾的 '\0'
+pub fn sys_getcwd(buf: *mut u8, size: usize) -> i64 {
+    sys_call(SyscallId::GetCwd, buf as usize, size, 0, 0, 0, 0)
+}
+
+pub fn sys_chdir(path: *const u8) -> i64 {
+    sys_call(SyscallId::Chdir, path as usize, 0, 0, 0, 0, 0)
+}
+
+pub fn sys_mkdir(path: *const u8) -> i64 {
+    sys_call(SyscallId::Mkdir, path as usize, 0, 0, 0, 0, 0)
+}
+
+// flags 含有 AT_REMOVEDIR 时删除空目录
+pub fn sys_unlink(path: *const u8, flags: usize) -> i64 {
+    sys_call(SyscallId::Unlink, path as usize, flags, 0, 0, 0, 0)
+}
+
+pub fn sys_rename(old_path: *const u8, new_path: *const u8) -> i64 {
+    sys_call(
+        SyscallId::Rename,
+        old_path as usize,
+        new_path as usize,
+        0,
+        0,
+        0,
+        0,
+    )
+}
+
+// 以 linux_dirent64 的格式读出目录项，返回写入的字节数，读完时返回 0
+pub fn sys_getdents(fd: usize, buf: *mut u8, len: usize) -> i64 {
+    sys_call(SyscallId::GetDents, fd, buf as usize, len, 0, 0, 0)
+}
+
 pub fn sys_open(path: *const u8, flags: i32) -> i64 {
-    sys_call(SyscallId::Open, path as usize, flags as usize, 0, 0)
+    sys_call(SyscallId::Open, path as usize, flags as usize, 0, 0, 0, 0)
 }
 
 pub fn sys_close(fd: i32) -> i64 {
-    sys_call(SyscallId::Close, fd as usize, 0, 0, 0)
+    sys_call(SyscallId::Close, fd as usize, 0, 0, 0, 0, 0)
+}
+
+// fds[0] 为读端，fds[1] 为写端
+pub fn sys_pipe(fds: &mut [i32; 2]) -> i64 {
+    sys_call(SyscallId::Pipe, fds.as_mut_ptr() as usize, 0, 0, 0, 0, 0)
 }
 
 pub fn sys_write(fd: usize, base: *const u8, len: usize) -> i64 {
-    sys_call(SyscallId::Write, fd, base as usize, len, 0)
+    sys_call(SyscallId::Write, fd, base as usize, len, 0, 0, 0)
+}
+
+// offset 可以为负，成功时返回新的偏移
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> i64 {
+    sys_call(SyscallId::Lseek, fd, offset as usize, whence, 0, 0, 0)
+}
+
+pub fn sys_pwrite(fd: usize, base: *const u8, len: usize, offset: usize) -> i64 {
+    sys_call(SyscallId::Pwrite, fd, base as usize, len, offset, 0, 0)
+}
+
+pub fn sys_stat(path: *const u8, stat: *mut Stat) -> i64 {
+    sys_call(SyscallId::Stat, path as usize, stat as usize, 0, 0, 0, 0)
+}
+
+pub fn sys_fstat(fd: usize, stat: *mut Stat) -> i64 {
+    sys_call(SyscallId::Fstat, fd, stat as usize, 0, 0, 0, 0)
 }
 
 pub fn sys_exit(code: usize) -> ! {
-    sys_call(SyscallId::Exit, code, 0, 0, 0);
+    sys_call(SyscallId::Exit, code, 0, 0, 0, 0, 0);
     loop {}
 }
 
-pub fn sys_read(fd: usize, base: *mut u8, len: usize) -> i64 {
-    sys_call(SyscallId::Read, fd, base as usize, len, 0)
+pub fn sys_read(fd: usize, base: *const u8, len: usize) -> i64 {
+    sys_call(SyscallId::Read, fd, base as usize, len, 0, 0, 0)
+}
+
+pub fn sys_pread(fd: usize, base: *mut u8, len: usize, offset: usize) -> i64 {
+    sys_call(SyscallId::Pread, fd, base as usize, len, offset, 0, 0)
+}
+
+// argv 为以空指针结尾的参数数组，成功时不会返回
+pub fn sys_exec(path: *const u8, argv: *const *const u8) -> i64 {
+    sys_call(SyscallId::Exec, path as usize, argv as usize, 0, 0, 0, 0)
+}
+
+pub fn sys_fork() -> i64 {
+    sys_call(SyscallId::Fork, 0, 0, 0, 0, 0, 0)
+}
+
+pub fn sys_yield() {
+    sys_call(SyscallId::Yield, 0, 0, 0, 0, 0, 0);
+}
+
+pub fn set_priority(priority: usize) -> i64 {
+    sys_call(SyscallId::SetPriority, priority, 0, 0, 0, 0, 0)
+}
+
+// 返回以毫秒为单位的时间
+pub fn sys_gettime() -> usize {
+    sys_call(SyscallId::GetTime, 0, 0, 0, 0, 0, 0) as usize
+}
+
+pub fn sys_getpid() -> usize {
+    sys_call(SyscallId::GetPid, 0, 0, 0, 0, 0, 0) as usize
+}
+
+pub fn sys_getppid() -> usize {
+    sys_call(SyscallId::GetPPid, 0, 0, 0, 0, 0, 0) as usize
+}
+
+// 将堆顶设为 addr，返回新的堆顶；失败或 addr 为 0 时返回当前的堆顶
+pub fn sys_brk(addr: usize) -> i64 {
+    sys_call(SyscallId::Brk, addr, 0, 0, 0, 0, 0)
+}
+
+pub fn sys_mmap(
+    addr: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: usize,
+    offset: usize,
+) -> i64 {
+    sys_call(SyscallId::Mmap, addr, len, prot, flags, fd, offset)
+}
+
+pub fn sys_munmap(addr: usize, len: usize) -> i64 {
+    sys_call(SyscallId::Munmap, addr, len, 0, 0, 0, 0)
+}
+
+pub fn sys_mprotect(addr: usize, len: usize, prot: usize) -> i64 {
+    sys_call(SyscallId::Mprotect, addr, len, prot, 0, 0, 0)
+}
+
+pub fn sys_msync(addr: usize, len: usize, flags: usize) -> i64 {
+    sys_call(SyscallId::Msync, addr, len, flags, 0, 0, 0)
 }
 
-pub fn sys_exec(path: *const u8) {
-    sys_call(SyscallId::Exec, path as usize, 0, 0, 0);
+// pid 为 0 时等待任意子进程，成功时返回其 pid 并将退出码写入 code
+pub fn sys_wait(pid: usize, code: *mut i32) -> i64 {
+    sys_call(SyscallId::Wait, pid, code as usize, 0, 0, 0, 0)
 }